@@ -79,6 +79,7 @@ async fn main() -> anyhow::Result<()> {
         let elapsed = start_time.elapsed();
         println!("----------------------------------------");
         println!("{}", &response.to_string());
+        println!("Outcome: {:?}, tokens used: {}", response.outcome, response.usage.total());
         println!("Time taken for response: {:?}", elapsed);
     }
 
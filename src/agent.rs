@@ -1,31 +1,152 @@
 use crate::executor::{ToolExecutor, ToolResult};
-use crate::provider::{Message, Provider, StopReason};
+use crate::provider::{ContentPart, Message, Provider, StopReason, ToolCall, ToolChoice, Usage};
 use crate::tool::ToolRegistry;
-use anyhow::Result;
+use crate::error::{AxonerError, Result};
+use anyhow::anyhow;
 use crate::file_session_manager::FileSessionManager;
-use crate::session::Session;
+use crate::session::{RunState, Session};
+use crate::context::{prepare_context, ContextStrategy, SummaryCall};
+use crate::run_result::{AgentRunResult, AgentStep, RunOutcome};
+use crate::handoff::Handoff;
+use crate::plan::{answer_prompt, parse_steps, planning_prompt, revision_prompt, step_prompt, Plan};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct Agent {
+    name: String,
     provider: Box<dyn Provider>,
     registry: ToolRegistry,
     max_iterations: usize,
     system_prompt: Option<String>,
-    file_session_manager: Option<FileSessionManager>
+    file_session_manager: Option<FileSessionManager>,
+    context_strategy: ContextStrategy,
+    token_budget: Option<u32>,
+    max_continuations: usize,
+    handoffs: Vec<Handoff>,
+    max_plan_steps: Option<usize>,
+    initial_tool_choice: Option<ToolChoice>,
+    cancelled: Arc<AtomicBool>
+}
+
+const CONTINUE_PROMPT: &str = "Your previous response was cut off by the output token limit. \
+Continue exactly where you left off, without repeating anything.";
+
+/// Stops a running agent before its next LLM call
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
 }
 
 impl Agent {
     pub fn new(provider: Box<dyn Provider>, registry: ToolRegistry, system_prompt: Option<String>, file_session_manager: Option<FileSessionManager>) -> Self {
         Self {
+            name: "agent".to_string(),
             provider,
             registry,
             max_iterations: 10, // Prevent infinite loops
             system_prompt,
-            file_session_manager
+            file_session_manager,
+            context_strategy: ContextStrategy::Full,
+            token_budget: None,
+            max_continuations: 0,
+            handoffs: Vec::new(),
+            max_plan_steps: None,
+            initial_tool_choice: None,
+            cancelled: Arc::new(AtomicBool::new(false))
+        }
+    }
+
+    /// Name reported in run results and used for handoff tools
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Let the model hand the conversation over to `agent`, which continues the
+    /// same session with its own system prompt, provider and tools. Fails if
+    /// another handoff already has the same agent name or tool name; name agents
+    /// with `with_name`.
+    pub fn with_handoff(mut self, agent: Agent, description: impl Into<String>) -> Result<Self> {
+        let handoff = Handoff::new(agent, description);
+        if let Some(existing) = self
+            .handoffs
+            .iter()
+            .find(|h| h.agent.name == handoff.agent.name || h.tool_name() == handoff.tool_name())
+        {
+            return Err(anyhow!(
+                "Agent {} already hands off to {} (tool {}); give the agents distinct names",
+                self.name,
+                existing.agent.name,
+                existing.tool_name()
+            )
+            .into());
         }
+        self.handoffs.push(handoff);
+        Ok(self)
+    }
+
+    /// Choose how the session history is trimmed before each provider call
+    pub fn with_context_strategy(mut self, strategy: ContextStrategy) -> Self {
+        self.context_strategy = strategy;
+        self
+    }
+
+    /// Maximum number of LLM turns per run
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Stop runs that use more than `max_tokens` input plus output tokens
+    pub fn with_token_budget(mut self, max_tokens: u32) -> Self {
+        self.token_budget = Some(max_tokens);
+        self
+    }
+
+    /// When the model stops at its output token limit, ask it to continue up to
+    /// `max_continuations` times and join the pieces into one answer
+    pub fn with_max_continuations(mut self, max_continuations: usize) -> Self {
+        self.max_continuations = max_continuations;
+        self
+    }
+
+    /// Plan-and-execute mode: the model first writes a plan, each step is carried
+    /// out by the tool loop, and the plan is revised after every step. At most
+    /// `max_steps` steps are executed before the final answer.
+    pub fn with_planning(mut self, max_steps: usize) -> Self {
+        self.max_plan_steps = Some(max_steps);
+        self
+    }
+
+    /// Tool choice for the first LLM turn of each run, e.g. to force a search
+    /// before answering. Later turns leave the choice to the model. In planning
+    /// mode this applies to the first turn of the first step.
+    pub fn with_initial_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.initial_tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Handle for cancelling the current or next run from another task
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
     }
 
     /// Run the agent with a user prompt
-    pub async fn run(&self, user_prompt: &str) -> Result<String> {
+    pub async fn run(&self, user_prompt: &str) -> Result<AgentRunResult> {
+        self.run_with_parts(user_prompt, Vec::new()).await
+    }
+
+    /// Run the agent with a user prompt and attached images or documents
+    pub async fn run_with_parts(&self, user_prompt: &str, parts: Vec<ContentPart>) -> Result<AgentRunResult> {
 
         let mut session = if let Some(ref sm) = self.file_session_manager {
             if sm.exists() { sm.load()? }
@@ -39,88 +160,526 @@ impl Agent {
 
         println!();
 
-        session.add_message(Message {
-            role: "user".to_string(),
-            content: user_prompt.to_string(),
+        self.start_run(&mut session, user_prompt, parts, self.file_session_manager.as_ref()).await
+    }
+
+    /// Run the agent on a caller-owned session instead of its session manager's.
+    /// The session is updated in place and not persisted.
+    pub async fn run_with_session(&self, session: &mut Session, user_prompt: &str) -> Result<AgentRunResult> {
+        self.start_run(session, user_prompt, Vec::new(), None).await
+    }
+
+    async fn start_run(
+        &self,
+        session: &mut Session,
+        user_prompt: &str,
+        parts: Vec<ContentPart>,
+        store: Option<&FileSessionManager>,
+    ) -> Result<AgentRunResult> {
+        match self.max_plan_steps {
+            Some(max_steps) => self.run_planned(session, user_prompt, parts, store, max_steps).await,
+            None => {
+                let message = Message { parts, ..Message::user(user_prompt) };
+                self.run_prompt(session, message, store, self.initial_tool_choice.clone()).await
+            }
+        }
+    }
+
+    /// Answer one prompt with the tool loop
+    async fn run_prompt(
+        &self,
+        session: &mut Session,
+        message: Message,
+        store: Option<&FileSessionManager>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<AgentRunResult> {
+        session.add_message(message);
+        session.set_run_state(Some(RunState::default()));
+        checkpoint(store, session)?;
+
+        self.run_loop(self, session, store, self.new_result(), Instant::now(), tool_choice).await
+    }
+
+    /// Continue a run that was interrupted by an error or cancelled, from the last
+    /// checkpoint saved in the session. Runs that ended with any other outcome,
+    /// such as `MaxIterations` or `BudgetExceeded`, are finished and can't be
+    /// resumed. Tool calls that were requested but whose
+    /// results were not recorded are executed again, and a run that was handed off
+    /// continues with the agent in control. For planned runs only the interrupted
+    /// step is resumed.
+    pub async fn resume(&self) -> Result<AgentRunResult> {
+        let sm = self
+            .file_session_manager
+            .as_ref()
+            .ok_or_else(|| AxonerError::session_store("Cannot resume a stateless agent"))?;
+        let mut session = sm.load()?;
+        let Some(state) = session.run_state() else {
+            return Err(AxonerError::session_store(format!(
+                "Session {} has no interrupted run to resume",
+                sm.get_session()
+            )));
+        };
+
+        let agent = state
+            .active_agent
+            .as_deref()
+            .and_then(|name| self.find_agent(name))
+            .unwrap_or(self);
+        // A planned run may be resuming any step, so only plain runs force a tool again
+        let tool_choice = self.initial_tool_choice.clone().filter(|_| self.max_plan_steps.is_none());
+        agent.run_loop(self, &mut session, Some(sm), self.new_result(), Instant::now(), tool_choice).await
+    }
+
+    /// Plan the task, carry out each step with the tool loop, revise the plan after
+    /// every step and finally answer from the step results. The task and its
+    /// attached parts are recorded in the session, so the planner and every step
+    /// see them along with the earlier conversation.
+    async fn run_planned(
+        &self,
+        session: &mut Session,
+        task: &str,
+        parts: Vec<ContentPart>,
+        store: Option<&FileSessionManager>,
+        max_steps: usize,
+    ) -> Result<AgentRunResult> {
+        let started = Instant::now();
+        let mut result = AgentRunResult {
+            outcome: RunOutcome::Completed,
+            ..self.new_result()
+        };
+
+        session.add_message(Message { parts, ..Message::user(task) });
+        checkpoint(store, session)?;
+
+        let prompt = planning_prompt(task, &self.registry.get_all_for_llm());
+        let reply = self.plan_turn(session, Message::user(prompt), &mut result).await?;
+        let mut plan = match parse_steps(&reply) {
+            Some(steps) if !steps.is_empty() => Plan::new(steps),
+            // Without a usable plan the task is a single step
+            _ => Plan::new(vec![task.to_string()]),
+        };
+        result.steps.push(AgentStep::Planned {
+            revision: 0,
+            steps: plan.pending_descriptions(),
+        });
+
+        let mut executed = 0;
+        while let Some(index) = plan.next_pending() {
+            if executed >= max_steps {
+                plan.skip_pending();
+                break;
+            }
+            executed += 1;
+            println!("📋 Step {}: {}", index + 1, plan.steps[index].description);
+
+            let message = Message::user(step_prompt(task, &plan, index));
+            // The initial tool choice only applies to the first step
+            let tool_choice = self.initial_tool_choice.clone().filter(|_| executed == 1);
+            let step = self.run_prompt(session, message, store, tool_choice).await?;
+            let outcome = step.outcome.clone();
+            plan.record(index, step.text.clone(), outcome.clone());
+            absorb(&mut result, step);
+
+            let over_budget = self
+                .token_budget
+                .is_some_and(|budget| result.usage.total() > budget);
+            if matches!(outcome, RunOutcome::Cancelled | RunOutcome::BudgetExceeded) || over_budget {
+                let outcome = if over_budget { RunOutcome::BudgetExceeded } else { outcome };
+                plan.skip_pending();
+                result.plan = Some(plan);
+                return Ok(finish(result, outcome, None, started));
+            }
+
+            let reply = self
+                .plan_turn(session, Message::user(revision_prompt(task, &plan)), &mut result)
+                .await?;
+            if let Some(steps) = parse_steps(&reply)
+                && plan.revise(steps)
+            {
+                result.steps.push(AgentStep::Planned {
+                    revision: plan.revisions,
+                    steps: plan.pending_descriptions(),
+                });
+            }
+        }
+
+        let answer = self
+            .run_prompt(session, Message::user(answer_prompt(task, &plan)), store, None)
+            .await?;
+        let outcome = answer.outcome.clone();
+        let text = answer.text.clone();
+        absorb(&mut result, answer);
+        result.plan = Some(plan);
+        Ok(finish(result, outcome, text, started))
+    }
+
+    /// One planner call without tools, on the session's context followed by
+    /// `message`, recorded as an LLM turn. The message isn't kept in the session.
+    async fn plan_turn(
+        &self,
+        session: &mut Session,
+        message: Message,
+        result: &mut AgentRunResult,
+    ) -> Result<String> {
+        let context = prepare_context(
+            &self.context_strategy,
+            self.provider.as_ref(),
+            session,
+            self.system_prompt.as_deref(),
+            &[],
+        )
+        .await?;
+        if let Some(call) = context.summary_call {
+            record_summary_call(result, call, result.iterations);
+        }
+        let mut messages = context.messages;
+        messages.push(message);
+
+        let started = Instant::now();
+        let response = self
+            .provider
+            .complete(messages, None, None, context.system_prompt, None)
+            .await?;
+
+        result.usage += response.usage;
+        result.steps.push(AgentStep::LlmTurn {
+            iteration: result.iterations,
+            text: response.text.clone(),
+            reasoning: response.reasoning.clone(),
+            tool_calls: Vec::new(),
+            stop_reason: response.stop_reason,
+            usage: response.usage,
+            duration: started.elapsed(),
         });
+        result.iterations += 1;
+        Ok(response.text.unwrap_or_default())
+    }
+
+    /// This agent or one reachable through its handoffs
+    fn find_agent(&self, name: &str) -> Option<&Agent> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.handoffs.iter().find_map(|h| h.agent.find_agent(name))
+    }
 
+    fn new_result(&self) -> AgentRunResult {
+        AgentRunResult {
+            text: None,
+            outcome: RunOutcome::MaxIterations,
+            answered_by: self.name.clone(),
+            steps: Vec::new(),
+            usage: Usage::default(),
+            iterations: 0,
+            duration: Duration::ZERO,
+            plan: None,
+        }
+    }
+
+    /// The tool loop. `origin` is the agent the run started with, whose cancel
+    /// handle and token budget stay in force after handoffs; `result` carries the
+    /// steps and usage of the run so far. `initial_tool_choice` applies to the
+    /// first LLM turn only.
+    async fn run_loop(
+        &self,
+        origin: &Agent,
+        session: &mut Session,
+        store: Option<&FileSessionManager>,
+        mut result: AgentRunResult,
+        started: Instant,
+        initial_tool_choice: Option<ToolChoice>,
+    ) -> Result<AgentRunResult> {
         let executor = ToolExecutor::new(&self.registry);
-        let tools = self.registry.get_all_for_llm();
+        let mut tools = self.registry.get_all_for_llm();
+        tools.extend(self.handoffs.iter().map(Handoff::as_tool));
+        let mut state = session.run_state().cloned().unwrap_or_default();
+        result.answered_by = self.name.clone();
+
+        if !state.pending_tool_calls.is_empty() {
+            let pending = std::mem::take(&mut state.pending_tool_calls);
+            let tool_results = execute_tools(&executor, &pending, state.iteration, &mut result.steps).await?;
+            session.add_message(tool_results_message(&tool_results));
+            state.iteration += 1;
+            session.set_run_state(Some(state.clone()));
+            checkpoint(store, session)?;
+        }
 
-        for _iteration in 1..=self.max_iterations {
+        while state.iteration < self.max_iterations {
+
+            if origin.cancelled.swap(false, Ordering::SeqCst) {
+                return Ok(finish(result, RunOutcome::Cancelled, None, started));
+            }
 
+            let context = prepare_context(
+                &self.context_strategy,
+                self.provider.as_ref(),
+                session,
+                self.system_prompt.as_deref(),
+                &tools,
+            )
+            .await?;
+
+            // A summary written to fit the context counts as a turn of this run
+            if let Some(call) = context.summary_call {
+                record_summary_call(&mut result, call, state.iteration);
+                if origin.token_budget.is_some_and(|budget| result.usage.total() > budget) {
+                    end_run(store, session)?;
+                    return Ok(finish(result, RunOutcome::BudgetExceeded, None, started));
+                }
+                checkpoint(store, session)?;
+            }
+
+            let first_turn = state.iteration == 0 && state.continuations == 0;
+            let tool_choice = initial_tool_choice.clone().filter(|_| first_turn);
+
+            let turn_started = Instant::now();
             let response = self
                 .provider
-                .complete(session.get_messages().clone(), Some(tools.clone()), None, self.system_prompt.clone())
+                .complete(context.messages, Some(tools.clone()), None, context.system_prompt, tool_choice)
                 .await?;
 
+            result.usage += response.usage;
+            result.iterations += 1;
+            result.steps.push(AgentStep::LlmTurn {
+                iteration: state.iteration,
+                text: response.text.clone(),
+                reasoning: response.reasoning.clone(),
+                tool_calls: response.tool_calls.clone(),
+                stop_reason: response.stop_reason.clone(),
+                usage: response.usage,
+                duration: turn_started.elapsed(),
+            });
+
+            let over_budget = origin
+                .token_budget
+                .is_some_and(|budget| result.usage.total() > budget);
+            if over_budget && response.stop_reason != StopReason::EndTurn {
+                end_run(store, session)?;
+                return Ok(finish(result, RunOutcome::BudgetExceeded, None, started));
+            }
+
             match response.stop_reason {
                 StopReason::EndTurn => {
-                    if let Some(text) = response.text {
+                    session.set_run_state(None);
+                    if let Some(ref text) = response.text {
                         session.add_message(Message {
-                            role: "assistant".to_string(),
-                            content: text.clone(),
+                            thinking: response.thinking.clone(),
+                            ..Message::assistant(text.clone())
                         });
-
-                        if let Some(ref sm) = self.file_session_manager {
-                            sm.save(&session)?;
-                        }
-
-                        println!("Response from Agent:");
-                        return Ok(text);
-                    } else {
-                        return Ok("(No response from agent)".to_string());
                     }
+                    checkpoint(store, session)?;
+
+                    println!("Response from Agent:");
+                    let text = join_partial(state.partial_text, response.text);
+                    return Ok(finish(result, RunOutcome::Completed, text, started));
                 }
 
                 StopReason::ToolUse => {
                     // LLM wants to use tools
-                    println!("Entered here");
                     if let Some(text) = &response.text {
                         println!("💭 Agent thinking: {}", text);
                     }
 
                     if response.tool_calls.is_empty() {
-                        return Ok("Agent wanted to use tools but didn't specify any".to_string());
+                        return Err(anyhow!("Model requested tool use without any tool calls").into());
                     }
 
-                    // Execute the tools
-                    let tool_results = executor.execute_all(&response.tool_calls).await?;
+                    // A handoff takes precedence over any other tool calls in the turn
+                    if let Some((call, handoff)) = self.find_handoff(&response.tool_calls) {
+                        return self
+                            .hand_off(origin, handoff, call, session, store, state, result, started)
+                            .await;
+                    }
 
-                    // Add assistant's tool use to messages
+                    // Record the tool calls before running them, so an interrupted
+                    // run can pick them up again
                     session.add_message(Message {
-                        role: "assistant".to_string(),
-                        content: format_tool_use(&response.tool_calls),
+                        thinking: response.thinking.clone(),
+                        ..Message::assistant(format_tool_use(&response.tool_calls))
                     });
+                    state.pending_tool_calls = response.tool_calls.clone();
+                    session.set_run_state(Some(state.clone()));
+                    checkpoint(store, session)?;
+
+                    // Execute the tools
+                    let tool_results =
+                        execute_tools(&executor, &response.tool_calls, state.iteration, &mut result.steps).await?;
 
                     // Add tool results to messages
-                    session.add_message(Message {
-                        role: "user".to_string(),
-                        content: format_tool_results(&tool_results),
-                    });
+                    session.add_message(tool_results_message(&tool_results));
+                    state.pending_tool_calls.clear();
+                    state.iteration += 1;
+                    session.set_run_state(Some(state.clone()));
+                    checkpoint(store, session)?;
 
                     println!();
                     // Continue the loop
                 }
 
                 StopReason::MaxTokens => {
-                    return Ok("Agent hit max tokens limit".to_string());
+                    let Some(partial) = response.text.filter(|text| !text.is_empty()) else {
+                        end_run(store, session)?;
+                        return Ok(finish(result, RunOutcome::MaxTokens, join_partial(state.partial_text, None), started));
+                    };
+                    if state.continuations >= self.max_continuations {
+                        end_run(store, session)?;
+                        let text = join_partial(state.partial_text, Some(partial));
+                        return Ok(finish(result, RunOutcome::MaxTokens, text, started));
+                    }
+
+                    // Keep the cut-off answer and ask the model to carry on
+                    session.add_message(Message {
+                        thinking: response.thinking.clone(),
+                        ..Message::assistant(partial.clone())
+                    });
+                    session.add_message(Message::user(CONTINUE_PROMPT));
+                    state.partial_text.push_str(&partial);
+                    state.continuations += 1;
+                    session.set_run_state(Some(state.clone()));
+                    checkpoint(store, session)?;
+                }
+
+                StopReason::ContentFilter => {
+                    end_run(store, session)?;
+                    return Ok(finish(result, RunOutcome::ContentFiltered, None, started));
+                }
+
+                StopReason::Error => {
+                    return Err(anyhow!("Provider stopped with an error").into());
                 }
 
-                _ => {
-                    return Ok(format!("Agent stopped with reason: {:?}", response.stop_reason));
+                StopReason::Other(reason) => {
+                    return Err(anyhow!("Provider stopped with unsupported reason: {}", reason).into());
                 }
             }
         }
 
-        if let Some(ref sm) = self.file_session_manager {
-            sm.save(&session)?;
-        }
-        Ok(format!(
-            "Agent reached max iterations ({})",
-            self.max_iterations
-        ))
+        end_run(store, session)?;
+        Ok(finish(result, RunOutcome::MaxIterations, None, started))
+    }
+
+    fn find_handoff<'a>(&'a self, tool_calls: &'a [ToolCall]) -> Option<(&'a ToolCall, &'a Handoff)> {
+        tool_calls.iter().find_map(|call| {
+            self.handoffs
+                .iter()
+                .find(|h| h.tool_name() == call.name)
+                .map(|h| (call, h))
+        })
+    }
+
+    /// Record the handoff in the session and let the target agent finish the run
+    #[allow(clippy::too_many_arguments)]
+    async fn hand_off(
+        &self,
+        origin: &Agent,
+        handoff: &Handoff,
+        call: &ToolCall,
+        session: &mut Session,
+        store: Option<&FileSessionManager>,
+        mut state: RunState,
+        mut result: AgentRunResult,
+        started: Instant,
+    ) -> Result<AgentRunResult> {
+        let target = &handoff.agent;
+        println!("🤝 Handing off from {} to {}", self.name, target.name);
+
+        session.add_message(Message::assistant(format_tool_use(std::slice::from_ref(call))));
+        session.add_message(Message::user(format!(
+            "Tool '{}' returned: Transferred to the {} agent",
+            call.name, target.name
+        )));
+        result.steps.push(AgentStep::Handoff {
+            iteration: state.iteration,
+            from: self.name.clone(),
+            to: target.name.clone(),
+            reason: call.input["reason"].as_str().map(str::to_string),
+        });
+
+        state.pending_tool_calls.clear();
+        state.iteration += 1;
+        state.active_agent = Some(target.name.clone());
+        session.set_run_state(Some(state));
+        checkpoint(store, session)?;
+
+        Box::pin(target.run_loop(origin, session, store, result, started, None)).await
+    }
+}
+
+/// Persist the session, including its run state, if there is a session manager
+fn checkpoint(store: Option<&FileSessionManager>, session: &Session) -> Result<()> {
+    if let Some(sm) = store {
+        sm.save(session)?;
+    }
+    Ok(())
+}
+
+/// Record the summary call made while preparing a context as an LLM turn
+fn record_summary_call(result: &mut AgentRunResult, call: SummaryCall, iteration: usize) {
+    let response = call.response;
+    result.usage += response.usage;
+    result.iterations += 1;
+    result.steps.push(AgentStep::LlmTurn {
+        iteration,
+        text: response.text,
+        reasoning: response.reasoning,
+        tool_calls: Vec::new(),
+        stop_reason: response.stop_reason,
+        usage: response.usage,
+        duration: call.duration,
+    });
+}
+
+/// Clear the checkpoint of a run that reached a final outcome, so that only
+/// runs interrupted by an error or cancelled are left to resume
+fn end_run(store: Option<&FileSessionManager>, session: &mut Session) -> Result<()> {
+    session.set_run_state(None);
+    checkpoint(store, session)
+}
+
+fn finish(mut result: AgentRunResult, outcome: RunOutcome, text: Option<String>, started: Instant) -> AgentRunResult {
+    result.outcome = outcome;
+    result.text = text;
+    result.duration = started.elapsed();
+    result
+}
+
+/// Add the steps and usage of a plan step's run to the planned run
+fn absorb(result: &mut AgentRunResult, mut step: AgentRunResult) {
+    result.steps.append(&mut step.steps);
+    result.usage += step.usage;
+    result.iterations += step.iterations;
+    result.answered_by = step.answered_by;
+}
+
+/// Prefix an answer with the text of earlier cut-off responses
+fn join_partial(partial: String, text: Option<String>) -> Option<String> {
+    match text {
+        _ if partial.is_empty() => text,
+        Some(text) => Some(partial + &text),
+        None => Some(partial),
+    }
+}
+
+/// Execute tool calls one by one, recording each as a step
+async fn execute_tools(
+    executor: &ToolExecutor<'_>,
+    tool_calls: &[ToolCall],
+    iteration: usize,
+    steps: &mut Vec<AgentStep>,
+) -> Result<Vec<ToolResult>> {
+    let mut results = Vec::new();
+    for call in tool_calls {
+        let started = Instant::now();
+        let result = executor.execute(call).await?;
+        steps.push(AgentStep::ToolCall {
+            iteration,
+            call: call.clone(),
+            result: result.result.clone(),
+            duration: started.elapsed(),
+        });
+        results.push(result);
     }
+    Ok(results)
 }
 
 fn format_tool_use(tool_calls: &[crate::provider::ToolCall]) -> String {
@@ -137,11 +696,202 @@ fn format_tool_use(tool_calls: &[crate::provider::ToolCall]) -> String {
         .join("\n")
 }
 
-/// Format tool results for feeding back to the LLM
-fn format_tool_results(results: &[ToolResult]) -> String {
-    results
+/// Format tool results for feeding back to the LLM, with any images or
+/// documents the tools returned attached
+fn tool_results_message(results: &[ToolResult]) -> Message {
+    let text = results
         .iter()
         .map(|result| format!("Tool '{}' returned: {}", result.tool_name, result.result))
         .collect::<Vec<_>>()
-        .join("\n")
-}
\ No newline at end of file
+        .join("\n");
+    Message {
+        parts: results.iter().flat_map(|result| result.parts.clone()).collect(),
+        ..Message::user(text)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProvider;
+    use crate::provider::CompletionResponse;
+    use crate::tools::calculator::Calculator;
+    use serde_json::json;
+
+    fn calculator_agent(provider: &MockProvider) -> Agent {
+        let mut registry = ToolRegistry::new();
+        registry.register(Box::new(Calculator));
+        Agent::new(Box::new(provider.clone()), registry, None, None)
+    }
+
+    #[tokio::test]
+    async fn runs_a_tool_then_answers() {
+        let provider = MockProvider::new()
+            .with_tool_call("calculator", json!({ "operation": "add", "a": 2, "b": 3 }))
+            .with_text("2 + 3 = 5");
+        let result = calculator_agent(&provider).run("What is 2 + 3?").await.unwrap();
+
+        assert!(result.is_completed());
+        assert_eq!(result.text.as_deref(), Some("2 + 3 = 5"));
+        assert_eq!(result.iterations, 2);
+        provider.assert_all_consumed();
+        provider.assert_request_count(2);
+        provider.assert_tool_offered("calculator");
+        provider.assert_last_message_contains("Tool 'calculator' returned: 5");
+    }
+
+    #[tokio::test]
+    async fn stops_after_max_iterations() {
+        let provider = MockProvider::new()
+            .with_tool_call("calculator", json!({ "operation": "add", "a": 1, "b": 1 }))
+            .with_tool_call("calculator", json!({ "operation": "add", "a": 2, "b": 2 }));
+        let agent = calculator_agent(&provider).with_max_iterations(2);
+        let result = agent.run("Keep adding").await.unwrap();
+
+        assert_eq!(result.outcome, RunOutcome::MaxIterations);
+        assert_eq!(result.text, None);
+        provider.assert_all_consumed();
+    }
+
+    #[tokio::test]
+    async fn runs_stopped_at_max_iterations_are_not_resumable() {
+        let dir = std::env::temp_dir().join(format!("axonerai-test-{}", uuid::Uuid::new_v4()));
+        let manager = FileSessionManager::new("limited".to_string(), dir.clone()).unwrap();
+        let provider = MockProvider::new()
+            .with_tool_call("calculator", json!({ "operation": "add", "a": 1, "b": 1 }))
+            .with_text("unused");
+        let mut registry = ToolRegistry::new();
+        registry.register(Box::new(Calculator));
+        let agent = Agent::new(Box::new(provider.clone()), registry, None, Some(manager)).with_max_iterations(1);
+
+        assert_eq!(agent.run("Add").await.unwrap().outcome, RunOutcome::MaxIterations);
+        assert!(agent.resume().await.is_err());
+        assert_eq!(provider.remaining(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn continues_answers_cut_off_by_max_tokens() {
+        let provider = MockProvider::new()
+            .with_stop("The first half, ", StopReason::MaxTokens)
+            .with_text("and the second half.");
+        let agent = calculator_agent(&provider).with_max_continuations(1);
+        let result = agent.run("Write something long").await.unwrap();
+
+        assert!(result.is_completed());
+        assert_eq!(result.text.as_deref(), Some("The first half, and the second half."));
+        provider.assert_last_message_contains(CONTINUE_PROMPT);
+    }
+
+    #[tokio::test]
+    async fn stops_at_max_tokens_without_continuations() {
+        let provider = MockProvider::new().with_stop("Cut off", StopReason::MaxTokens);
+        let result = calculator_agent(&provider).run("Write something long").await.unwrap();
+
+        assert_eq!(result.outcome, RunOutcome::MaxTokens);
+        assert_eq!(result.text.as_deref(), Some("Cut off"));
+    }
+
+    #[tokio::test]
+    async fn counts_summary_calls_against_the_token_budget() {
+        let summary = CompletionResponse {
+            text: Some("The user asked about the weather.".to_string()),
+            tool_calls: Vec::new(),
+            stop_reason: StopReason::EndTurn,
+            usage: Usage { input_tokens: 1990, output_tokens: 10, ..Default::default() },
+            reasoning: None,
+            thinking: Vec::new(),
+        };
+        let provider = MockProvider::new().with_response(summary).with_text("Sunny.");
+        let agent = calculator_agent(&provider)
+            .with_context_strategy(ContextStrategy::Summarize { max_tokens: Some(1), keep_recent: 1 })
+            .with_token_budget(100);
+        let mut session = Session::new("summarized".to_string());
+        session.add_message(Message::user("What's the weather like?"));
+        session.add_message(Message::assistant("Let me check."));
+
+        let result = agent.run_with_session(&mut session, "And tomorrow?").await.unwrap();
+
+        assert_eq!(result.outcome, RunOutcome::BudgetExceeded);
+        assert_eq!(result.usage.total(), 2000);
+        assert_eq!(result.iterations, 1);
+        assert!(matches!(&result.steps[0], AgentStep::LlmTurn { usage, .. } if usage.total() == 2000));
+        assert!(session.summary().is_some());
+        assert_eq!(provider.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn planner_sees_the_earlier_conversation() {
+        let provider = MockProvider::new()
+            .with_text(r#"{"steps": ["Look up the population of Lyon"]}"#)
+            .with_text("Lyon has about 520,000 inhabitants.")
+            .with_text(r#"{"steps": []}"#)
+            .with_text("About 520,000 people live in Lyon.");
+        let agent = calculator_agent(&provider).with_planning(3);
+        let mut session = Session::new("planned".to_string());
+        session.add_message(Message::user("How many people live in Paris?"));
+        session.add_message(Message::assistant("About 2.1 million."));
+
+        let result = agent.run_with_session(&mut session, "Now do the same for Lyon").await.unwrap();
+
+        assert!(result.is_completed());
+        let planner = &provider.requests()[0];
+        assert_eq!(planner.messages[0].content, "How many people live in Paris?");
+        assert_eq!(planner.messages[2].content, "Now do the same for Lyon");
+        assert!(planner.tools.is_none());
+        assert_eq!(session.get_messages()[2].content, "Now do the same for Lyon");
+        provider.assert_all_consumed();
+    }
+
+    #[tokio::test]
+    async fn hands_off_to_another_agent() {
+        let triage_provider = MockProvider::new()
+            .with_tool_call("transfer_to_billing", json!({ "reason": "refund request" }));
+        let billing_provider = MockProvider::new().with_text("Your refund is on its way.");
+        let billing = Agent::new(Box::new(billing_provider.clone()), ToolRegistry::new(), None, None)
+            .with_name("billing");
+        let triage = Agent::new(Box::new(triage_provider.clone()), ToolRegistry::new(), None, None)
+            .with_name("triage")
+            .with_handoff(billing, "Refunds and invoices")
+            .unwrap();
+
+        let result = triage.run("I want a refund").await.unwrap();
+
+        assert!(result.is_completed());
+        assert_eq!(result.answered_by, "billing");
+        assert_eq!(result.text.as_deref(), Some("Your refund is on its way."));
+        assert!(result.steps.iter().any(|step| matches!(
+            step,
+            AgentStep::Handoff { from, to, .. } if from == "triage" && to == "billing"
+        )));
+        triage_provider.assert_tool_offered("transfer_to_billing");
+        triage_provider.assert_all_consumed();
+        billing_provider.assert_all_consumed();
+    }
+
+    #[tokio::test]
+    async fn resumes_after_a_provider_error() {
+        let dir = std::env::temp_dir().join(format!("axonerai-test-{}", uuid::Uuid::new_v4()));
+        let manager = FileSessionManager::new("resume".to_string(), dir.clone()).unwrap();
+        let provider = MockProvider::new()
+            .with_tool_call("calculator", json!({ "operation": "multiply", "a": 6, "b": 7 }))
+            .with_error(anyhow!("connection reset").into())
+            .with_text("6 * 7 = 42");
+        let mut registry = ToolRegistry::new();
+        registry.register(Box::new(Calculator));
+        let agent = Agent::new(Box::new(provider.clone()), registry, None, Some(manager));
+
+        assert!(agent.run("What is 6 * 7?").await.is_err());
+        let result = agent.resume().await.unwrap();
+
+        assert!(result.is_completed());
+        assert_eq!(result.text.as_deref(), Some("6 * 7 = 42"));
+        // The tool ran before the error and isn't called again
+        assert!(!result.steps.iter().any(|step| matches!(step, AgentStep::ToolCall { .. })));
+        provider.assert_last_message_contains("Tool 'calculator' returned: 42");
+        provider.assert_all_consumed();
+        assert!(agent.resume().await.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,13 +1,69 @@
-use crate::provider::{CompletionResponse, Message, Provider, StopReason, Tool, ToolCall};
-use anyhow::{anyhow, Result};
+use crate::context::context_limit;
+use crate::http::{endpoint, HttpConfig};
+use crate::tokens::{ApproximateCounter, TokenCounter};
+use crate::provider::{CompletionResponse, Message, Provider, StopReason, ThinkingBlock, Tool, ToolCall, ToolChoice, Usage};
+use crate::error::{check_response, Result};
+use anyhow::anyhow;
 use async_trait::async_trait;
 use serde::{Deserialize};
 use serde_json::{json, Value};
 
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// A message to mark as a prompt cache breakpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheBreakpoint {
+    /// The message at this index
+    Index(usize),
+    /// The message this many places from the end; `FromEnd(0)` is the last one
+    FromEnd(usize),
+}
+
+/// Which parts of a request Anthropic may cache. Everything up to and including a
+/// marked part is cached, and the API accepts at most 4 breakpoints per request.
+#[derive(Debug, Clone, Default)]
+pub struct PromptCaching {
+    pub system_prompt: bool,
+    pub tools: bool,
+    pub messages: Vec<CacheBreakpoint>,
+}
+
+impl PromptCaching {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_system_prompt(mut self) -> Self {
+        self.system_prompt = true;
+        self
+    }
+
+    pub fn with_tools(mut self) -> Self {
+        self.tools = true;
+        self
+    }
+
+    pub fn with_message_breakpoint(mut self, breakpoint: CacheBreakpoint) -> Self {
+        self.messages.push(breakpoint);
+        self
+    }
+
+    fn caches_message(&self, index: usize, count: usize) -> bool {
+        self.messages.iter().any(|b| match *b {
+            CacheBreakpoint::Index(i) => i == index,
+            CacheBreakpoint::FromEnd(n) => n < count && count - 1 - n == index,
+        })
+    }
+}
+
 pub struct AnthropicProvider {
     api_key: String,
     model: String,
     client: reqwest::Client,
+    base_url: String,
+    prompt_caching: PromptCaching,
+    thinking_budget: Option<u32>,
 }
 
 impl AnthropicProvider {
@@ -16,6 +72,9 @@ impl AnthropicProvider {
             api_key,
             model: "claude-sonnet-4-20250514".to_string(),
             client: reqwest::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            prompt_caching: PromptCaching::default(),
+            thinking_budget: None,
         }
     }
 
@@ -23,6 +82,45 @@ impl AnthropicProvider {
         self.model = model;
         self
     }
+
+    /// Send requests through a caller-configured client, which may be shared
+    /// with other providers
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Replace the API base URL, including the version path (default
+    /// `https://api.anthropic.com/v1`)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Mark the system prompt, tool definitions or messages as cacheable, so
+    /// long prompts repeated across calls are billed at the cache rate
+    pub fn with_prompt_caching(mut self, prompt_caching: PromptCaching) -> Self {
+        self.prompt_caching = prompt_caching;
+        self
+    }
+
+    /// Enable extended thinking with up to `budget_tokens` of reasoning per
+    /// response. `max_tokens` is raised above the budget when needed. Requests
+    /// that force tool use with `ToolChoice::Required` or `ToolChoice::Tool`
+    /// fail, since Anthropic doesn't allow them with thinking.
+    pub fn with_thinking(mut self, budget_tokens: u32) -> Self {
+        self.thinking_budget = Some(budget_tokens);
+        self
+    }
+
+    /// Build a client from `config`, and use its base URL if it sets one
+    pub fn with_http_config(mut self, config: &HttpConfig) -> Result<Self> {
+        self.client = config.build_client()?;
+        if let Some(ref base_url) = config.base_url {
+            self.base_url = base_url.clone();
+        }
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -32,19 +130,43 @@ impl Provider for AnthropicProvider {
         messages: Vec<Message>,
         tools: Option<Vec<Tool>>,
         max_tokens: Option<u32>,
-        system_prompt: Option<String>
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>
     ) -> Result<CompletionResponse> {
 
+        let caching = &self.prompt_caching;
+        let system = match system_prompt {
+            Some(prompt) if caching.system_prompt => json!([cached_text(prompt)]),
+            prompt => json!(prompt),
+        };
+        let count = messages.len();
+        let messages: Vec<Value> = messages
+            .into_iter()
+            .enumerate()
+            .map(|(i, m)| message_json(m, caching.caches_message(i, count)))
+            .collect();
+
+        let mut max_tokens = max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        if let Some(budget) = self.thinking_budget
+            && max_tokens <= budget
+        {
+            max_tokens = budget + DEFAULT_MAX_TOKENS;
+        }
+
         let mut body = json!({
-            "system": system_prompt,
+            "system": system,
             "model": self.model,
-            "max_tokens": max_tokens.unwrap_or(4096),
+            "max_tokens": max_tokens,
             "messages": messages,
         });
 
+        if let Some(budget) = self.thinking_budget {
+            body["thinking"] = json!({ "type": "enabled", "budget_tokens": budget });
+        }
+
         // Add tools if provided
         if let Some(tools) = tools {
-            let anthropic_tools: Vec<Value> = tools
+            let mut anthropic_tools: Vec<Value> = tools
                 .iter()
                 .map(|t| {
                     json!({
@@ -54,12 +176,33 @@ impl Provider for AnthropicProvider {
                     })
                 })
                 .collect();
+            // A breakpoint on the last tool caches the whole tool list
+            if caching.tools && let Some(last) = anthropic_tools.last_mut() {
+                last["cache_control"] = json!({ "type": "ephemeral" });
+            }
             body["tools"] = json!(anthropic_tools);
+
+            if let Some(choice) = tool_choice {
+                // Anthropic rejects forced tool use together with extended thinking
+                if self.thinking_budget.is_some() && matches!(choice, ToolChoice::Required | ToolChoice::Tool(_)) {
+                    return Err(anyhow!(
+                        "Anthropic does not support forcing tool use ({:?}) with extended thinking; use ToolChoice::Auto or disable thinking",
+                        choice
+                    )
+                    .into());
+                }
+                body["tool_choice"] = match choice {
+                    ToolChoice::Auto => json!({ "type": "auto" }),
+                    ToolChoice::None => json!({ "type": "none" }),
+                    ToolChoice::Required => json!({ "type": "any" }),
+                    ToolChoice::Tool(name) => json!({ "type": "tool", "name": name }),
+                };
+            }
         }
 
         let response = self
             .client
-            .post("https://api.anthropic.com/v1/messages")
+            .post(endpoint(&self.base_url, "messages"))
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
@@ -67,17 +210,14 @@ impl Provider for AnthropicProvider {
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            return Err(anyhow!("Anthropic API error {}: {}", status, error_text));
-        }
+        let response = check_response("Anthropic", response).await?;
 
         let api_response: AnthropicResponse = response.json().await?;
         
         // Parse the response into our unified format
         let mut text_parts = Vec::new();
         let mut tool_calls = Vec::new();
+        let mut thinking = Vec::new();
 
         for content in api_response.content {
             match content.content_type.as_str() {
@@ -86,6 +226,16 @@ impl Provider for AnthropicProvider {
                         text_parts.push(text);
                     }
                 }
+                "thinking" => {
+                    if let (Some(text), Some(signature)) = (content.thinking, content.signature) {
+                        thinking.push(ThinkingBlock::Thinking { thinking: text, signature });
+                    }
+                }
+                "redacted_thinking" => {
+                    if let Some(data) = content.data {
+                        thinking.push(ThinkingBlock::RedactedThinking { data });
+                    }
+                }
                 "tool_use" => {
                     if let (Some(id), Some(name), Some(input)) = (content.id, content.name, content.input) {
                         tool_calls.push(ToolCall { id, name, input });
@@ -101,19 +251,78 @@ impl Provider for AnthropicProvider {
             Some(text_parts.join("\n"))
         };
 
+        let reasoning = thinking
+            .iter()
+            .filter_map(|block| match block {
+                ThinkingBlock::Thinking { thinking, .. } => Some(thinking.as_str()),
+                ThinkingBlock::RedactedThinking { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        let reasoning = (!reasoning.is_empty()).then(|| reasoning.join("\n"));
+
         let stop_reason = match api_response.stop_reason.as_str() {
             "tool_use" => StopReason::ToolUse,
             "end_turn" => StopReason::EndTurn,
             "max_tokens" => StopReason::MaxTokens,
-            _ => StopReason::Error,
+            "stop_sequence" => StopReason::EndTurn,
+            "refusal" => StopReason::ContentFilter,
+            other => StopReason::Other(other.to_string()),
         };
 
         Ok(CompletionResponse {
             text,
             tool_calls,
             stop_reason,
+            usage: Usage {
+                input_tokens: api_response.usage.input_tokens,
+                output_tokens: api_response.usage.output_tokens,
+                cache_creation_input_tokens: api_response.usage.cache_creation_input_tokens.unwrap_or(0),
+                cache_read_input_tokens: api_response.usage.cache_read_input_tokens.unwrap_or(0),
+            },
+            reasoning,
+            thinking,
         })
     }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn fingerprint(&self) -> Value {
+        json!({ "base_url": self.base_url, "thinking_budget": self.thinking_budget })
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        context_limit(&self.model)
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        Box::new(ApproximateCounter::anthropic())
+    }
+}
+
+/// A text content block marked as a cache breakpoint
+fn cached_text(text: String) -> Value {
+    json!({ "type": "text", "text": text, "cache_control": { "type": "ephemeral" } })
+}
+
+/// A message in Anthropic's format. Thinking blocks kept with an assistant
+/// message go back before its text, signature intact, and images and documents
+/// go between them; `ContentPart` already serializes as Anthropic blocks.
+fn message_json(message: Message, cache: bool) -> Value {
+    if message.thinking.is_empty() && message.parts.is_empty() && !cache {
+        return json!({ "role": message.role, "content": message.content });
+    }
+    let mut content: Vec<Value> = message.thinking.iter().map(|block| json!(block)).collect();
+    content.extend(message.parts.iter().map(|part| json!(part)));
+    // Empty text blocks are rejected, so a message may consist of parts only
+    if !message.content.is_empty() || content.is_empty() {
+        content.push(json!({ "type": "text", "text": message.content }));
+    }
+    if cache && let Some(last) = content.last_mut() {
+        last["cache_control"] = json!({ "type": "ephemeral" });
+    }
+    json!({ "role": message.role, "content": content })
 }
 
 // Anthropic API response structures - not tested
@@ -121,6 +330,16 @@ impl Provider for AnthropicProvider {
 struct AnthropicResponse {
     content: Vec<ContentBlock>,
     stop_reason: String,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_creation_input_tokens: Option<u32>,
+    cache_read_input_tokens: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -135,4 +354,7 @@ struct ContentBlock {
     name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     input: Option<Value>,
+    thinking: Option<String>,
+    signature: Option<String>,
+    data: Option<String>,
 }
\ No newline at end of file
@@ -0,0 +1,263 @@
+use crate::error::Result;
+use crate::provider::{CompletionResponse, Message, Provider, StopReason, Tool, ToolChoice};
+use crate::tokens::TokenCounter;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A response kept by a cache backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub response: CompletionResponse,
+    pub stored_at: DateTime<Utc>,
+}
+
+/// Storage for cached responses, keyed by request hash
+pub trait CacheBackend: Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    fn put(&self, key: &str, entry: CachedResponse) -> Result<()>;
+}
+
+/// In-memory cache that evicts the least recently used response when full
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, CachedResponse>>,
+}
+
+impl MemoryCache {
+    /// Keep up to `capacity` responses. Fails for 0, which couldn't cache anything.
+    pub fn new(capacity: usize) -> Result<Self> {
+        let capacity = NonZeroUsize::new(capacity).ok_or_else(|| anyhow!("Cache capacity must be at least 1"))?;
+        Ok(Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        })
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, entry: CachedResponse) -> Result<()> {
+        self.entries.lock().unwrap().put(key.to_string(), entry);
+        Ok(())
+    }
+}
+
+/// Cache that keeps one JSON file per response, so it survives restarts
+pub struct DiskCache {
+    directory: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(directory: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    /// Delete every cached response
+    pub fn clear(&self) -> Result<()> {
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_file(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{}.json", key))
+    }
+}
+
+impl CacheBackend for DiskCache {
+    /// Unreadable or corrupted entries count as misses
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let content = fs::read_to_string(self.entry_file(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn put(&self, key: &str, entry: CachedResponse) -> Result<()> {
+        fs::write(self.entry_file(key), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+/// Turns caching off for a `CachingProvider` from anywhere, e.g. after it was
+/// handed to an agent
+#[derive(Debug, Clone)]
+pub struct CacheBypass(Arc<AtomicBool>);
+
+impl CacheBypass {
+    pub fn set(&self, bypass: bool) {
+        self.0.store(bypass, Ordering::SeqCst);
+    }
+}
+
+/// Serves repeated identical requests from a cache instead of calling the provider.
+/// Requests are keyed on a hash of the model, provider settings, messages, tools,
+/// tool choice, system prompt and `max_tokens`. Errors and responses that stopped
+/// with an error or an unknown reason are not cached.
+pub struct CachingProvider {
+    inner: Box<dyn Provider>,
+    backend: Box<dyn CacheBackend>,
+    ttl: Option<Duration>,
+    bypass: Arc<AtomicBool>,
+    strict_writes: bool,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Box<dyn Provider>, backend: Box<dyn CacheBackend>) -> Self {
+        Self {
+            inner,
+            backend,
+            ttl: None,
+            bypass: Arc::new(AtomicBool::new(false)),
+            strict_writes: false,
+        }
+    }
+
+    /// Treat cached responses older than `ttl` as misses
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Skip cache lookups and always call the provider. Fresh responses are
+    /// still stored, so this also refreshes the cache.
+    pub fn with_bypass(self, bypass: bool) -> Self {
+        self.bypass.store(bypass, Ordering::SeqCst);
+        self
+    }
+
+    /// Fail requests whose response can't be stored, instead of printing a
+    /// warning and returning the response uncached
+    pub fn with_strict_writes(mut self) -> Self {
+        self.strict_writes = true;
+        self
+    }
+
+    /// Handle for switching the bypass on and off later
+    pub fn bypass_handle(&self) -> CacheBypass {
+        CacheBypass(self.bypass.clone())
+    }
+
+    fn is_fresh(&self, entry: &CachedResponse) -> bool {
+        match self.ttl {
+            Some(ttl) => (Utc::now() - entry.stored_at).to_std().is_ok_and(|age| age <= ttl),
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CachingProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let key = cache_key(
+            self.inner.as_ref(),
+            &messages,
+            tools.as_deref(),
+            max_tokens,
+            system_prompt.as_deref(),
+            tool_choice.as_ref(),
+        );
+
+        if !self.bypass.load(Ordering::SeqCst)
+            && let Some(entry) = self.backend.get(&key)
+            && self.is_fresh(&entry)
+        {
+            return Ok(entry.response);
+        }
+
+        let response = self.inner.complete(messages, tools, max_tokens, system_prompt, tool_choice).await?;
+        if !matches!(response.stop_reason, StopReason::Error | StopReason::Other(_)) {
+            let entry = CachedResponse {
+                response: response.clone(),
+                stored_at: Utc::now(),
+            };
+            if let Err(e) = self.backend.put(&key, entry) {
+                if self.strict_writes {
+                    return Err(e);
+                }
+                println!("⚠️ Failed to cache response: {}", e);
+            }
+        }
+        Ok(response)
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.inner.model()
+    }
+
+    fn fingerprint(&self) -> Value {
+        self.inner.fingerprint()
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        self.inner.context_limit()
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        self.inner.token_counter()
+    }
+}
+
+/// Hex SHA-256 of everything that decides a response, including the
+/// provider's model and settings
+pub fn cache_key(
+    provider: &dyn Provider,
+    messages: &[Message],
+    tools: Option<&[Tool]>,
+    max_tokens: Option<u32>,
+    system_prompt: Option<&str>,
+    tool_choice: Option<&ToolChoice>,
+) -> String {
+    let mut request = json!({
+        "model": provider.model(),
+        "messages": messages,
+        "tools": tools,
+        "max_tokens": max_tokens,
+        "system_prompt": system_prompt,
+    });
+    // Only hashed when set, so entries stored without them stay valid
+    if let Some(choice) = tool_choice {
+        request["tool_choice"] = json!(choice);
+    }
+    let settings = provider.fingerprint();
+    if !settings.is_null() {
+        request["provider"] = settings;
+    }
+    Sha256::digest(request.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_cache_rejects_zero_capacity() {
+        assert!(MemoryCache::new(0).is_err());
+        assert!(MemoryCache::new(1).is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::error::Result;
+use crate::provider::{CompletionResponse, Message, Provider, Tool, ToolChoice};
+use crate::tokens::{ApproximateCounter, TokenCounter};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// One recorded provider call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request in normalized form, used for matching during replay
+    pub request: Value,
+    pub response: CompletionResponse,
+}
+
+/// A file of recorded provider calls
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Model of the recorded provider
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Context window of the recorded provider, reported again during replay
+    pub context_limit: Option<u32>,
+    pub interactions: Vec<Interaction>,
+}
+
+enum Mode {
+    Record(Box<dyn Provider>),
+    Replay,
+}
+
+/// Records the calls made to a real provider to a cassette file, or serves them
+/// back from the file so tests run without network access
+pub struct CassetteProvider {
+    mode: Mode,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+    /// Which recorded interactions have been served in replay mode
+    served: Mutex<Vec<bool>>,
+    /// Model named in the cassette, reported in replay mode
+    recorded_model: Option<String>,
+    token_counter: Option<Box<dyn Fn() -> Box<dyn TokenCounter> + Send + Sync>>,
+}
+
+impl CassetteProvider {
+    /// Pass requests through to `inner` and write every request and response to
+    /// `path`, replacing any earlier recording
+    pub fn record(inner: Box<dyn Provider>, path: PathBuf) -> Self {
+        let cassette = Cassette {
+            model: inner.model().map(str::to_string),
+            context_limit: inner.context_limit(),
+            interactions: Vec::new(),
+        };
+        Self {
+            mode: Mode::Record(inner),
+            path,
+            cassette: Mutex::new(cassette),
+            served: Mutex::new(Vec::new()),
+            recorded_model: None,
+            token_counter: None,
+        }
+    }
+
+    /// Serve the responses recorded at `path`. Requests that match no unused
+    /// recording fail with an error.
+    pub fn replay(path: PathBuf) -> Result<Self> {
+        let cassette: Cassette = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(Self {
+            mode: Mode::Replay,
+            path,
+            served: Mutex::new(vec![false; cassette.interactions.len()]),
+            recorded_model: cassette.model.clone(),
+            cassette: Mutex::new(cassette),
+            token_counter: None,
+        })
+    }
+
+    /// Token counter used in replay mode, which should match the recorded
+    /// provider's so context strategies trim the history the same way
+    pub fn with_token_counter(mut self, counter: impl Fn() -> Box<dyn TokenCounter> + Send + Sync + 'static) -> Self {
+        self.token_counter = Some(Box::new(counter));
+        self
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn save(&self, cassette: &Cassette) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(cassette)?)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for CassetteProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let request = normalize_request(
+            &messages,
+            tools.as_deref(),
+            max_tokens,
+            system_prompt.as_deref(),
+            tool_choice.as_ref(),
+        );
+
+        match &self.mode {
+            Mode::Record(inner) => {
+                let response = inner.complete(messages, tools, max_tokens, system_prompt, tool_choice).await?;
+                let mut cassette = self.cassette.lock().unwrap();
+                cassette.interactions.push(Interaction {
+                    request,
+                    response: response.clone(),
+                });
+                self.save(&cassette)?;
+                Ok(response)
+            }
+            Mode::Replay => {
+                let cassette = self.cassette.lock().unwrap();
+                let mut served = self.served.lock().unwrap();
+                // Identical requests are served in the order they were recorded
+                let index = cassette
+                    .interactions
+                    .iter()
+                    .enumerate()
+                    .position(|(i, interaction)| !served[i] && interaction.request == request)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "No unused recording in cassette {} matches request: {}",
+                            self.path.display(),
+                            request
+                        )
+                    })?;
+                served[index] = true;
+                Ok(cassette.interactions[index].response.clone())
+            }
+        }
+    }
+
+    fn model(&self) -> Option<&str> {
+        match &self.mode {
+            Mode::Record(inner) => inner.model(),
+            Mode::Replay => self.recorded_model.as_deref(),
+        }
+    }
+
+    fn fingerprint(&self) -> Value {
+        match &self.mode {
+            Mode::Record(inner) => inner.fingerprint(),
+            Mode::Replay => Value::Null,
+        }
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        match &self.mode {
+            Mode::Record(inner) => inner.context_limit(),
+            Mode::Replay => self.cassette.lock().unwrap().context_limit,
+        }
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        match (&self.mode, &self.token_counter) {
+            (Mode::Record(inner), _) => inner.token_counter(),
+            (Mode::Replay, Some(counter)) => counter(),
+            (Mode::Replay, None) => Box::new(ApproximateCounter::default()),
+        }
+    }
+}
+
+/// The parts of a request that decide the response, with whitespace collapsed
+/// and tools sorted by name so formatting differences don't break matching
+pub fn normalize_request(
+    messages: &[Message],
+    tools: Option<&[Tool]>,
+    max_tokens: Option<u32>,
+    system_prompt: Option<&str>,
+    tool_choice: Option<&ToolChoice>,
+) -> Value {
+    let mut tools: Vec<&Tool> = tools.into_iter().flatten().collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut request = json!({
+        "system_prompt": system_prompt.map(normalize_text),
+        "messages": messages
+            .iter()
+            .map(|m| {
+                let mut message = json!({ "role": m.role, "content": normalize_text(&m.content) });
+                if !m.parts.is_empty() {
+                    message["parts"] = json!(m.parts);
+                }
+                message
+            })
+            .collect::<Vec<_>>(),
+        "tools": tools
+            .iter()
+            .map(|t| json!({ "name": t.name, "description": normalize_text(&t.description), "input_schema": t.input_schema }))
+            .collect::<Vec<_>>(),
+        "max_tokens": max_tokens,
+    });
+    // Only present when set, so cassettes recorded without it still match
+    if let Some(choice) = tool_choice {
+        request["tool_choice"] = json!(choice);
+    }
+    request
+}
+
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AxonerError;
+
+    #[test]
+    fn replaying_a_missing_cassette_is_an_io_error() {
+        let path = std::env::temp_dir().join(format!("axonerai-test-{}.json", uuid::Uuid::new_v4()));
+        assert!(matches!(CassetteProvider::replay(path), Err(AxonerError::Io(_))));
+    }
+}
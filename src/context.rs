@@ -0,0 +1,273 @@
+use crate::provider::{CompletionResponse, Message, Provider, Tool};
+use crate::session::Session;
+use crate::tokens::TokenCounter;
+use crate::error::{AxonerError, Result};
+use anyhow::anyhow;
+use std::time::{Duration, Instant};
+
+/// How much of a session's history the agent sends to the provider on each call
+#[derive(Debug, Clone, Default)]
+pub enum ContextStrategy {
+    /// Send the whole history
+    #[default]
+    Full,
+    /// Send only the most recent messages. The turn starting at the last user
+    /// message is always sent, even when it is longer than `max_messages`.
+    SlidingWindow { max_messages: usize },
+    /// Drop the oldest messages until the request fits in `max_tokens`, or in the
+    /// model's context limit when `None`. Without either, the whole history is sent.
+    TokenBudget { max_tokens: Option<usize> },
+    /// Fold older messages into an LLM-written summary stored in the session once the
+    /// request outgrows the budget, keeping the `keep_recent` latest messages verbatim.
+    /// The budget works as for `TokenBudget`; without one nothing is summarized.
+    Summarize { max_tokens: Option<usize>, keep_recent: usize },
+}
+
+/// Messages and system prompt to send for one provider call
+#[derive(Debug, Clone)]
+pub struct PreparedContext {
+    pub messages: Vec<Message>,
+    pub system_prompt: Option<String>,
+    /// The summary call made while preparing, which the caller accounts for
+    pub summary_call: Option<SummaryCall>,
+}
+
+/// An LLM call made by `Summarize` to fold older messages into the summary
+#[derive(Debug, Clone)]
+pub struct SummaryCall {
+    pub response: CompletionResponse,
+    pub duration: Duration,
+}
+
+/// Tokens kept free for the model's reply when budgeting against a context limit
+const RESPONSE_RESERVE: usize = 4096;
+
+const SUMMARY_PROMPT: &str = "You summarize conversations between a user and an AI assistant. \
+Write a concise summary that keeps every fact, decision, tool result and open question \
+needed to continue the conversation. Reply with the summary only.";
+
+/// Context window sizes in tokens, matched by model name prefix (first match wins)
+const CONTEXT_LIMITS: &[(&str, u32)] = &[
+    ("claude-", 200_000),
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("openai/gpt-oss", 131_072),
+    ("llama-3.3-70b-versatile", 131_072),
+    ("llama-3.1-8b-instant", 131_072),
+];
+
+/// Context window size of a known model, in tokens
+pub fn context_limit(model: &str) -> Option<u32> {
+    CONTEXT_LIMITS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, limit)| *limit)
+}
+
+/// Select the messages to send for the next call according to `strategy`.
+/// `Summarize` may call the provider and updates the summary stored in `session`;
+/// that call is returned in `summary_call`.
+pub async fn prepare_context(
+    strategy: &ContextStrategy,
+    provider: &dyn Provider,
+    session: &mut Session,
+    system_prompt: Option<&str>,
+    tools: &[Tool],
+) -> Result<PreparedContext> {
+    let counter = provider.token_counter();
+    let messages = session.get_messages();
+    let overhead = system_prompt.map(|p| counter.count_text(p)).unwrap_or(0)
+        + counter.count_tools(tools);
+
+    let start = match strategy {
+        ContextStrategy::Full => 0,
+
+        ContextStrategy::SlidingWindow { max_messages } => {
+            let last_turn = messages.iter().rposition(|m| m.role == "user").unwrap_or(0);
+            turn_start(messages, messages.len().saturating_sub(*max_messages)).min(last_turn)
+        }
+
+        ContextStrategy::TokenBudget { max_tokens } => match budget(*max_tokens, provider) {
+            Some(budget) => {
+                // Count each message once and drop from a running total
+                let framing = counter.count_messages(&[]);
+                let sizes: Vec<usize> = messages
+                    .iter()
+                    .map(|m| counter.count_messages(std::slice::from_ref(m)).saturating_sub(framing))
+                    .collect();
+                let mut total = framing + sizes.iter().sum::<usize>();
+                let mut start = 0;
+                while start + 1 < messages.len() && overhead + total > budget {
+                    total -= sizes[start];
+                    start += 1;
+                }
+                turn_start(messages, start)
+            }
+            None => 0,
+        },
+
+        ContextStrategy::Summarize { max_tokens, keep_recent } => {
+            let summary_call = match budget(*max_tokens, provider) {
+                Some(budget) => {
+                    summarize_if_needed(provider, counter.as_ref(), session, overhead, budget, *keep_recent)
+                        .await?
+                }
+                None => None,
+            };
+            let start = session.summary().map(|s| s.message_count).unwrap_or(0);
+            return Ok(PreparedContext {
+                messages: session.get_messages()[start..].to_vec(),
+                system_prompt: with_summary(system_prompt, session),
+                summary_call,
+            });
+        }
+    };
+
+    Ok(PreparedContext {
+        messages: messages[start..].to_vec(),
+        system_prompt: system_prompt.map(str::to_string),
+        summary_call: None,
+    })
+}
+
+/// `max_tokens`, or the provider's context limit less room for the reply. `None`
+/// when neither is known, e.g. for unknown models or wrappers that don't forward
+/// `context_limit`.
+fn budget(max_tokens: Option<usize>, provider: &dyn Provider) -> Option<usize> {
+    max_tokens.or_else(|| {
+        provider
+            .context_limit()
+            .map(|limit| (limit as usize).saturating_sub(RESPONSE_RESERVE))
+    })
+}
+
+/// First user message at or after `from`, so the context never opens with an
+/// assistant turn. Falls back to `from` when there is none.
+fn turn_start(messages: &[Message], from: usize) -> usize {
+    messages[from..]
+        .iter()
+        .position(|m| m.role == "user")
+        .map(|offset| from + offset)
+        .unwrap_or(from)
+}
+
+async fn summarize_if_needed(
+    provider: &dyn Provider,
+    counter: &dyn TokenCounter,
+    session: &mut Session,
+    overhead: usize,
+    budget: usize,
+    keep_recent: usize,
+) -> Result<Option<SummaryCall>> {
+    let messages = session.get_messages();
+    let covered = session.summary().map(|s| s.message_count).unwrap_or(0);
+    let summary_tokens = session.summary().map(|s| counter.count_text(&s.text)).unwrap_or(0);
+
+    if overhead + summary_tokens + counter.count_messages(&messages[covered..]) <= budget {
+        return Ok(None);
+    }
+
+    // Keep the recent messages verbatim, starting on a user turn
+    let Some(cut) = (covered + 1..=messages.len().saturating_sub(keep_recent))
+        .rev()
+        .find(|&i| i < messages.len() && messages[i].role == "user")
+    else {
+        return Ok(None);
+    };
+
+    let mut transcript = String::new();
+    if let Some(summary) = session.summary() {
+        transcript.push_str(&format!("Summary so far:\n{}\n\n", summary.text));
+    }
+    transcript.push_str("Conversation:\n");
+    for message in &messages[covered..cut] {
+        transcript.push_str(&format!("{}: {}\n", message.role, message.content));
+    }
+
+    let started = Instant::now();
+    let response = provider
+        .complete(
+            vec![Message::user(transcript)],
+            None,
+            None,
+            Some(SUMMARY_PROMPT.to_string()),
+            None,
+        )
+        .await?;
+
+    let duration = started.elapsed();
+
+    let text = response
+        .text
+        .clone()
+        .ok_or_else(|| AxonerError::Other(anyhow!("Provider returned no conversation summary")))?;
+    session.set_summary(text, cut);
+    Ok(Some(SummaryCall { response, duration }))
+}
+
+fn with_summary(system_prompt: Option<&str>, session: &Session) -> Option<String> {
+    let Some(summary) = session.summary() else {
+        return system_prompt.map(str::to_string);
+    };
+    let summary = format!("Summary of the earlier conversation:\n{}", summary.text);
+    Some(match system_prompt {
+        Some(prompt) => format!("{}\n\n{}", prompt, summary),
+        None => summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProvider;
+
+    fn session() -> Session {
+        let mut session = Session::new("context".to_string());
+        session.add_message(Message::user("first question"));
+        session.add_message(Message::assistant("first answer"));
+        session.add_message(Message::user("second question"));
+        session.add_message(Message::assistant("Using tool 'calculator' with input: {}"));
+        session.add_message(Message::user("Tool 'calculator' returned: 4"));
+        session
+    }
+
+    async fn window(max_messages: usize) -> Vec<String> {
+        let strategy = ContextStrategy::SlidingWindow { max_messages };
+        let context = prepare_context(&strategy, &MockProvider::new(), &mut session(), None, &[])
+            .await
+            .unwrap();
+        context.messages.into_iter().map(|m| m.content).collect()
+    }
+
+    #[tokio::test]
+    async fn sliding_window_starts_on_a_user_turn() {
+        assert_eq!(
+            window(3).await,
+            vec!["second question", "Using tool 'calculator' with input: {}", "Tool 'calculator' returned: 4"]
+        );
+        assert_eq!(window(4).await.len(), 3);
+        assert_eq!(window(10).await.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn sliding_window_always_keeps_the_last_turn() {
+        assert_eq!(window(0).await, vec!["Tool 'calculator' returned: 4"]);
+        assert_eq!(window(1).await, vec!["Tool 'calculator' returned: 4"]);
+    }
+
+    #[tokio::test]
+    async fn token_budget_without_a_limit_sends_everything() {
+        let strategy = ContextStrategy::TokenBudget { max_tokens: None };
+        let context = prepare_context(&strategy, &MockProvider::new(), &mut session(), None, &[])
+            .await
+            .unwrap();
+        assert_eq!(context.messages.len(), 5);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::error::{AxonerError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+const ALGORITHM: &str = "xchacha20poly1305";
+
+/// A 256-bit key used to encrypt session files, identified by `id` so files
+/// written with an older key can still be read after rotation
+#[derive(Clone)]
+pub struct SessionKey {
+    id: String,
+    key: [u8; 32],
+}
+
+impl SessionKey {
+    pub fn new(id: impl Into<String>, key: [u8; 32]) -> Self {
+        Self { id: id.into(), key }
+    }
+
+    /// Create a key from the operating system's secure random generator
+    pub fn generate(id: impl Into<String>) -> Self {
+        Self::new(id, XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+/// Authenticated encryption for persisted sessions. New files are written with the
+/// current key; files written with any of the previous keys can still be read.
+#[derive(Debug, Clone)]
+pub struct SessionEncryption {
+    current: SessionKey,
+    previous: Vec<SessionKey>,
+}
+
+/// On-disk form of an encrypted session
+#[derive(Serialize, Deserialize)]
+struct EncryptedSession {
+    algorithm: String,
+    key_id: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl SessionEncryption {
+    pub fn new(current: SessionKey) -> Self {
+        Self {
+            current,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Keep accepting files encrypted with an older key
+    pub fn with_previous_key(mut self, key: SessionKey) -> Self {
+        self.previous.push(key);
+        self
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current.id
+    }
+
+    /// Encrypt serialized session data. The session id is bound as associated data,
+    /// so an encrypted file cannot be swapped in for another session.
+    pub fn encrypt(&self, session_id: &str, plaintext: &[u8]) -> Result<String> {
+        let cipher = XChaCha20Poly1305::new(&self.current.key.into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad: session_id.as_bytes() })
+            .map_err(|_| AxonerError::session_store(format!("Failed to encrypt session {}", session_id)))?;
+
+        Ok(serde_json::to_string(&EncryptedSession {
+            algorithm: ALGORITHM.to_string(),
+            key_id: self.current.id.clone(),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })?)
+    }
+
+    /// Decrypt a file written by `encrypt`, with whichever known key it names
+    pub fn decrypt(&self, session_id: &str, content: &str) -> Result<Vec<u8>> {
+        let envelope: EncryptedSession = serde_json::from_str(content)?;
+        if envelope.algorithm != ALGORITHM {
+            return Err(AxonerError::session_store(format!(
+                "Unsupported session encryption: {}",
+                envelope.algorithm
+            )));
+        }
+        let key = std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|k| k.id == envelope.key_id)
+            .ok_or_else(|| {
+                AxonerError::session_store(format!("No key {} to decrypt session {}", envelope.key_id, session_id))
+            })?;
+
+        let invalid = |_| AxonerError::session_store(format!("Corrupted encrypted session {}", session_id));
+        let nonce = STANDARD.decode(&envelope.nonce).map_err(invalid)?;
+        if nonce.len() != 24 {
+            return Err(AxonerError::session_store(format!("Invalid nonce in session {}", session_id)));
+        }
+        let ciphertext = STANDARD.decode(&envelope.ciphertext).map_err(invalid)?;
+        XChaCha20Poly1305::new(&key.key.into())
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: session_id.as_bytes() })
+            .map_err(|_| {
+                AxonerError::session_store(format!(
+                    "Failed to decrypt session {}: wrong key or tampered file",
+                    session_id
+                ))
+            })
+    }
+
+    /// Id of the key a stored file was encrypted with, `None` for plaintext files
+    pub fn key_id_of(content: &str) -> Option<String> {
+        serde_json::from_str::<EncryptedSession>(content)
+            .ok()
+            .map(|envelope| envelope.key_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encryption(id: &str, byte: u8) -> SessionEncryption {
+        SessionEncryption::new(SessionKey::new(id, [byte; 32]))
+    }
+
+    #[test]
+    fn round_trip() {
+        let encryption = encryption("k1", 1);
+        let content = encryption.encrypt("s1", b"{\"messages\":[]}").unwrap();
+        assert_eq!(SessionEncryption::key_id_of(&content).as_deref(), Some("k1"));
+        assert_eq!(encryption.decrypt("s1", &content).unwrap(), b"{\"messages\":[]}");
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let encryption = encryption("k1", 1);
+        let content = encryption.encrypt("s1", b"secret").unwrap();
+        let mut envelope: EncryptedSession = serde_json::from_str(&content).unwrap();
+        let mut ciphertext = STANDARD.decode(&envelope.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        envelope.ciphertext = STANDARD.encode(ciphertext);
+        let tampered = serde_json::to_string(&envelope).unwrap();
+        assert!(encryption.decrypt("s1", &tampered).is_err());
+    }
+
+    #[test]
+    fn file_cannot_be_swapped_between_sessions() {
+        let encryption = encryption("k1", 1);
+        let content = encryption.encrypt("s1", b"secret").unwrap();
+        assert!(encryption.decrypt("s2", &content).is_err());
+    }
+
+    #[test]
+    fn wrong_key_is_rejected() {
+        let content = encryption("k1", 1).encrypt("s1", b"secret").unwrap();
+        // Same key id, different key material
+        assert!(encryption("k1", 2).decrypt("s1", &content).is_err());
+        // Unknown key id
+        assert!(encryption("k2", 1).decrypt("s1", &content).is_err());
+    }
+
+    #[test]
+    fn previous_keys_decrypt_after_rotation() {
+        let old = SessionKey::new("old", [1; 32]);
+        let content = SessionEncryption::new(old.clone()).encrypt("s1", b"secret").unwrap();
+
+        let rotated = encryption("new", 2).with_previous_key(old);
+        assert_eq!(rotated.decrypt("s1", &content).unwrap(), b"secret");
+        let reencrypted = rotated.encrypt("s1", b"secret").unwrap();
+        assert_eq!(SessionEncryption::key_id_of(&reencrypted).as_deref(), Some("new"));
+        assert!(encryption("old", 1).decrypt("s1", &reencrypted).is_err());
+    }
+
+    #[test]
+    fn plaintext_has_no_key_id() {
+        assert_eq!(SessionEncryption::key_id_of("{\"session_id\":\"s1\"}"), None);
+    }
+}
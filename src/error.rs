@@ -0,0 +1,144 @@
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors returned by providers, tools, session storage and agents
+#[derive(Debug, Error)]
+pub enum AxonerError {
+    /// The provider answered with an unsuccessful HTTP status
+    #[error("{provider} API error {status}: {body}")]
+    ProviderHttp { provider: String, status: u16, body: String },
+
+    /// The provider rejected the request because of rate limits
+    #[error("{provider} rate limit exceeded: {body}")]
+    RateLimited { provider: String, retry_after: Option<Duration>, body: String },
+
+    /// The API key was missing, invalid or not allowed to use the model
+    #[error("{provider} authentication failed ({status}): {body}")]
+    Auth { provider: String, status: u16, body: String },
+
+    /// The request did not fit in the model's context window
+    #[error("{provider} context length exceeded: {body}")]
+    ContextLengthExceeded { provider: String, body: String },
+
+    /// The provider's response could not be understood
+    #[error("Invalid {provider} response: {message}")]
+    InvalidResponse { provider: String, message: String },
+
+    /// The request never got a response (connection, timeout, decoding)
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Tool not found: {0}")]
+    ToolNotFound(String),
+
+    #[error("Tool '{name}' failed: {source}")]
+    ToolFailed {
+        name: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// Reading, writing or decrypting persisted sessions failed
+    #[error("Session store error: {message}")]
+    SessionStore {
+        message: String,
+        #[source]
+        source: Option<std::io::Error>,
+    },
+
+    /// Imported conversation data is valid JSON but not a usable conversation
+    #[error("Invalid conversation import: {0}")]
+    Import(String),
+
+    /// A workflow graph is misconfigured or exceeded its step limits
+    #[error("Workflow error: {0}")]
+    Workflow(String),
+
+    /// File I/O outside session storage, such as caches, cassettes and checkpoints
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type Result<T, E = AxonerError> = std::result::Result<T, E>;
+
+impl AxonerError {
+    pub fn session_store(message: impl Into<String>) -> Self {
+        AxonerError::SessionStore {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// A failed read or write of session files
+    pub(crate) fn session_io(source: std::io::Error) -> Self {
+        AxonerError::SessionStore {
+            message: source.to_string(),
+            source: Some(source),
+        }
+    }
+
+    pub fn invalid_response(provider: &str, message: impl Into<String>) -> Self {
+        AxonerError::InvalidResponse {
+            provider: provider.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// HTTP status returned by the provider, if the error came from one
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            AxonerError::ProviderHttp { status, .. } | AxonerError::Auth { status, .. } => Some(*status),
+            AxonerError::RateLimited { .. } => Some(429),
+            AxonerError::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed if sent again later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AxonerError::RateLimited { .. } => true,
+            AxonerError::ProviderHttp { status, .. } => *status >= 500,
+            AxonerError::Http(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+/// Turn an unsuccessful provider response into the matching error
+pub(crate) async fn check_response(provider: &str, response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        // Negative, infinite or NaN values are ignored rather than trusted
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+    let body = response.text().await?;
+    let provider = provider.to_string();
+
+    Err(match status.as_u16() {
+        401 | 403 => AxonerError::Auth { provider, status: status.as_u16(), body },
+        429 => AxonerError::RateLimited { provider, retry_after, body },
+        400 | 413 if is_context_length_error(&body) => AxonerError::ContextLengthExceeded { provider, body },
+        status => AxonerError::ProviderHttp { provider, status, body },
+    })
+}
+
+fn is_context_length_error(body: &str) -> bool {
+    let body = body.to_lowercase();
+    ["context_length_exceeded", "context length", "prompt is too long", "too many tokens"]
+        .iter()
+        .any(|marker| body.contains(marker))
+}
@@ -1,6 +1,6 @@
-use crate::provider::ToolCall;
+use crate::provider::{ContentPart, ToolCall};
 use crate::tool::ToolRegistry;
-use anyhow::{anyhow, Result};
+use crate::error::{AxonerError, Result};
 
 /// Executes tool calls and returns results
 pub struct ToolExecutor<'a> {
@@ -19,19 +19,25 @@ impl<'a> ToolExecutor<'a> {
         let tool = self
             .registry
             .get(&tool_call.name)
-            .ok_or_else(|| anyhow!("Tool not found: {}", tool_call.name))?;
+            .ok_or_else(|| AxonerError::ToolNotFound(tool_call.name.clone()))?;
 
-        let result = tool.execute(tool_call.input.clone()).await?;
+        let output = tool
+            .execute_with_parts(tool_call.input.clone())
+            .await
+            .map_err(|source| AxonerError::ToolFailed {
+                name: tool_call.name.clone(),
+                source,
+            })?;
 
         Ok(ToolResult {
             tool_call_id: tool_call.id.clone(),
             tool_name: tool_call.name.clone(),
-            result,
+            result: output.text,
+            parts: output.parts,
         })
     }
 
     /// Execute multiple tool calls
-    
     pub async fn execute_all(&self, tool_calls: &[ToolCall]) -> Result<Vec<ToolResult>> {
         let mut results = Vec::new();
         for call in tool_calls {
@@ -47,4 +53,5 @@ pub struct ToolResult {
     pub tool_call_id: String,
     pub tool_name: String,
     pub result: String,
+    pub parts: Vec<ContentPart>,
 }
\ No newline at end of file
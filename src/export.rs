@@ -0,0 +1,192 @@
+use crate::provider::Message;
+use crate::session::Session;
+use crate::error::{AxonerError, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+impl Session {
+    /// Render the conversation as a Markdown transcript
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Session {}\n\n_Created {}, last updated {}_\n",
+            self.session_id(),
+            self.created_at().to_rfc3339(),
+            self.updated_at().to_rfc3339()
+        );
+        if let Some(summary) = self.summary() {
+            out.push_str(&format!(
+                "\n> **Summary of the first {} messages:** {}\n",
+                summary.message_count,
+                summary.text.replace('\n', "\n> ")
+            ));
+        }
+        for message in self.get_messages() {
+            out.push_str(&format!("\n## {}\n\n{}\n", role_heading(&message.role), message.content));
+        }
+        out
+    }
+
+    /// One line of OpenAI chat fine-tuning JSONL: `{"messages": [...]}`
+    pub fn to_openai_jsonl(&self, system_prompt: Option<&str>) -> String {
+        let mut messages = Vec::new();
+        if let Some(prompt) = system_prompt {
+            messages.push(json!({"role": "system", "content": prompt}));
+        }
+        for message in self.get_messages() {
+            messages.push(json!({"role": message.role, "content": message.content}));
+        }
+        json!({ "messages": messages }).to_string()
+    }
+
+    /// Anthropic Messages API body: `{"system": ..., "messages": [...]}`.
+    /// Consecutive messages with the same role are merged so roles alternate.
+    pub fn to_anthropic_json(&self, system_prompt: Option<&str>) -> Value {
+        let mut messages: Vec<Message> = Vec::new();
+        for message in self.get_messages() {
+            match messages.last_mut() {
+                Some(last) if last.role == message.role => {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&message.content);
+                }
+                _ => messages.push(text_message(&message.role, message.content.clone())),
+            }
+        }
+
+        let mut body = json!({ "messages": messages });
+        if let Some(prompt) = system_prompt {
+            body["system"] = json!(prompt);
+        }
+        body
+    }
+
+    /// Build a session from OpenAI chat messages, given either as an array or as a
+    /// `{"messages": [...]}` object. System messages are skipped, tool calls and tool
+    /// results are rendered as text the same way the agent records them.
+    pub fn from_openai_messages(session_id: String, value: &Value) -> Result<Session> {
+        let mut session = Session::new(session_id);
+        let mut tool_names = HashMap::new();
+
+        for message in message_array(value)? {
+            let role = message["role"].as_str().ok_or_else(|| AxonerError::Import("message without a role".to_string()))?;
+            match role {
+                "system" | "developer" => {}
+                "user" => session.add_message(text_message("user", content_text(&message["content"]))),
+                "assistant" => {
+                    let mut parts = Vec::new();
+                    let text = content_text(&message["content"]);
+                    if !text.is_empty() {
+                        parts.push(text);
+                    }
+                    for call in message["tool_calls"].as_array().into_iter().flatten() {
+                        let name = call["function"]["name"].as_str().unwrap_or_default();
+                        if let Some(id) = call["id"].as_str() {
+                            tool_names.insert(id.to_string(), name.to_string());
+                        }
+                        parts.push(format!(
+                            "Using tool '{}' with input: {}",
+                            name,
+                            call["function"]["arguments"].as_str().unwrap_or("{}")
+                        ));
+                    }
+                    session.add_message(text_message("assistant", parts.join("\n")));
+                }
+                "tool" => {
+                    let id = message["tool_call_id"].as_str().unwrap_or_default();
+                    let name = tool_names.get(id).map(String::as_str).unwrap_or(id);
+                    session.add_message(text_message(
+                        "user",
+                        format!("Tool '{}' returned: {}", name, content_text(&message["content"])),
+                    ));
+                }
+                other => {
+                    return Err(AxonerError::Import(format!("unsupported OpenAI message role: {}", other)))
+                }
+            }
+        }
+        Ok(session)
+    }
+
+    /// Build a session from Anthropic messages, given either as an array or as a
+    /// Messages API body. Tool use and tool result blocks are rendered as text.
+    pub fn from_anthropic_messages(session_id: String, value: &Value) -> Result<Session> {
+        let mut session = Session::new(session_id);
+        let mut tool_names = HashMap::new();
+
+        for message in message_array(value)? {
+            let role = message["role"].as_str().ok_or_else(|| AxonerError::Import("message without a role".to_string()))?;
+            if role != "user" && role != "assistant" {
+                return Err(AxonerError::Import(format!("unsupported Anthropic message role: {}", role)));
+            }
+
+            let content = match &message["content"] {
+                Value::String(text) => text.clone(),
+                Value::Array(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block["type"].as_str() {
+                        Some("text") => block["text"].as_str().map(str::to_string),
+                        Some("tool_use") => {
+                            let name = block["name"].as_str().unwrap_or_default();
+                            if let Some(id) = block["id"].as_str() {
+                                tool_names.insert(id.to_string(), name.to_string());
+                            }
+                            Some(format!("Using tool '{}' with input: {}", name, block["input"]))
+                        }
+                        Some("tool_result") => {
+                            let id = block["tool_use_id"].as_str().unwrap_or_default();
+                            let name = tool_names.get(id).map(String::as_str).unwrap_or(id);
+                            Some(format!("Tool '{}' returned: {}", name, content_text(&block["content"])))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            };
+            session.add_message(text_message(role, content));
+        }
+        Ok(session)
+    }
+}
+
+/// Export several sessions as OpenAI fine-tuning JSONL, one conversation per line
+pub fn sessions_to_openai_jsonl(sessions: &[Session], system_prompt: Option<&str>) -> String {
+    sessions
+        .iter()
+        .map(|s| s.to_openai_jsonl(system_prompt))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn role_heading(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn message_array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .or_else(|| value["messages"].as_array())
+        .ok_or_else(|| {
+            AxonerError::Import("expected an array of messages or an object with a \"messages\" array".to_string())
+        })
+}
+
+/// Text of a content field given as a string or an array of text parts
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn text_message(role: &str, content: String) -> Message {
+    Message::new(role, content)
+}
@@ -0,0 +1,127 @@
+use crate::error::{AxonerError, Result};
+use crate::provider::{CompletionResponse, Message, Provider, Tool, ToolChoice};
+use crate::tokens::{ApproximateCounter, TokenCounter};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Broad kinds of provider failure, used to decide whether another provider may do better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// 429 responses
+    RateLimited,
+    /// 5xx responses, including Anthropic's 529 "overloaded"
+    ServerError,
+    /// Connection failures and timeouts
+    Network,
+    /// Missing or rejected API key
+    Auth,
+    /// The prompt did not fit the model's context window
+    ContextLength,
+    /// A response that could not be parsed
+    InvalidResponse,
+    /// Any other 4xx response
+    ClientError,
+    /// Errors that did not come from the provider call
+    Other,
+}
+
+impl ErrorClass {
+    pub fn of(error: &AxonerError) -> Self {
+        match error {
+            AxonerError::RateLimited { .. } => ErrorClass::RateLimited,
+            AxonerError::ProviderHttp { status, .. } if *status >= 500 => ErrorClass::ServerError,
+            AxonerError::ProviderHttp { .. } => ErrorClass::ClientError,
+            AxonerError::Auth { .. } => ErrorClass::Auth,
+            AxonerError::ContextLengthExceeded { .. } => ErrorClass::ContextLength,
+            AxonerError::InvalidResponse { .. } => ErrorClass::InvalidResponse,
+            AxonerError::Http(e) if e.status().is_some_and(|s| s.is_server_error()) => ErrorClass::ServerError,
+            AxonerError::Http(e) if e.is_decode() => ErrorClass::InvalidResponse,
+            AxonerError::Http(_) => ErrorClass::Network,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// Tries an ordered list of providers, moving to the next one when a request
+/// fails with an error class configured for fallback
+pub struct FallbackProvider {
+    providers: Vec<Box<dyn Provider>>,
+    fall_back_on: HashSet<ErrorClass>,
+}
+
+impl FallbackProvider {
+    /// Falls back on rate limits, server and network errors, context length
+    /// errors and invalid responses
+    pub fn new(providers: Vec<Box<dyn Provider>>) -> Self {
+        Self {
+            providers,
+            fall_back_on: HashSet::from([
+                ErrorClass::RateLimited,
+                ErrorClass::ServerError,
+                ErrorClass::Network,
+                ErrorClass::ContextLength,
+                ErrorClass::InvalidResponse,
+            ]),
+        }
+    }
+
+    /// Replace the error classes that move a request on to the next provider
+    pub fn with_fallback_on(mut self, classes: &[ErrorClass]) -> Self {
+        self.fall_back_on = classes.iter().copied().collect();
+        self
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let mut last_error = None;
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider
+                .complete(messages.clone(), tools.clone(), max_tokens, system_prompt.clone(), tool_choice.clone())
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(e) if self.fall_back_on.contains(&ErrorClass::of(&e)) => {
+                    if index + 1 < self.providers.len() {
+                        println!("⚠️ Provider {} failed ({}), falling back to provider {}", index + 1, e, index + 2);
+                    }
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("FallbackProvider has no providers").into()))
+    }
+
+    /// The first provider's model, which serves requests unless it fails
+    fn model(&self) -> Option<&str> {
+        self.providers.first().and_then(|p| p.model())
+    }
+
+    /// Every provider's settings, since any of them may serve a request
+    fn fingerprint(&self) -> Value {
+        Value::Array(self.providers.iter().map(|p| json!([p.model(), p.fingerprint()])).collect())
+    }
+
+    /// The smallest known context window, so prepared context fits every provider
+    fn context_limit(&self) -> Option<u32> {
+        self.providers.iter().filter_map(|p| p.context_limit()).min()
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        match self.providers.first() {
+            Some(provider) => provider.token_counter(),
+            None => Box::new(ApproximateCounter::default()),
+        }
+    }
+}
@@ -1,49 +1,419 @@
-use anyhow::{Result};
-use std::path::PathBuf;
+use crate::error::{AxonerError, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::time::Duration;
+use crate::encryption::SessionEncryption;
 use crate::session::Session;
 
 pub struct FileSessionManager{
     session_id: String,
-    directory: PathBuf
+    directory: PathBuf,
+    encryption: Option<SessionEncryption>,
+    plaintext_migration: bool
+}
+
+/// Summary of a stored session, as returned by `list_sessions`
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub message_count: usize,
+    pub first_user_message: Option<String>,
+}
+
+/// A message matching a `search_sessions` query
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub session_id: String,
+    pub message_index: usize,
+    pub role: String,
+    pub content: String,
+}
+
+/// A session and the branches forked from it, as returned by `branch_tree`
+#[derive(Debug, Clone)]
+pub struct BranchNode {
+    pub session_id: String,
+    /// Number of parent messages the branch started with, `None` for the root
+    pub fork_index: Option<usize>,
+    pub active_branch: Option<String>,
+    pub children: Vec<BranchNode>,
+}
+
+/// Which sessions `apply_retention` should purge
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    /// Purge sessions not updated within this duration
+    pub max_age: Option<Duration>,
+    /// Keep only the most recently updated sessions
+    pub max_sessions: Option<usize>,
 }
 
 impl FileSessionManager {
     pub fn new(session_id: String, base_dir: PathBuf) -> Result<Self, std::io::Error>{
-        fs::create_dir_all(&base_dir.join(&session_id))?;
+        fs::create_dir_all(base_dir.join(&session_id))?;
         Ok(Self{
             session_id,
-            directory: base_dir
+            directory: base_dir,
+            encryption: None,
+            plaintext_migration: false
         })
     }
+
+    /// Encrypt session files at rest. Plaintext session files are rejected, so a
+    /// forged file can't stand in for an encrypted session; migrate existing ones
+    /// with `rotate_encryption` or `with_plaintext_migration`.
+    pub fn with_encryption(mut self, encryption: SessionEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Keep loading plaintext session files while encryption is configured, and
+    /// encrypt them the next time they are saved. Only enable this while
+    /// migrating a directory written before encryption was turned on.
+    pub fn with_plaintext_migration(mut self) -> Self {
+        self.plaintext_migration = true;
+        self
+    }
     fn session_path(&self)->PathBuf{
         self.directory.join(&self.session_id)
     }
 
-    pub fn save(&self, session: &Session)-> Result<(), std::io::Error>{
+    pub fn save(&self, session: &Session)-> Result<()>{
+        fs::create_dir_all(self.session_path()).map_err(AxonerError::session_io)?;
         let location = self.session_path().join("messages.json");
-        let message = serde_json::to_string(session)?;
-        fs::write(&location, message)?;
+        let message = self.encode_session(&self.session_id, session)?;
+        fs::write(&location, message).map_err(AxonerError::session_io)?;
         Ok(())
     }
 
     pub fn load(&self)->Result<Session>{
-        let location = self.session_path().join("messages.json");
-        let content = fs::read_to_string(location)?;
-        let session = serde_json::from_str(&content)?;
-        Ok(session)
+        self.read_session(&self.session_id)
     }
     pub fn exists(&self)->bool{
-
-        let path = &self.session_path().join("messages.json");
-        return if path.exists() {
-            true
-        } else { false }
-
+        self.session_path().join("messages.json").exists()
     }
 
     pub fn get_session(&self)->&str{
         &self.session_id
     }
 
-}
\ No newline at end of file
+    /// Load any session stored under the same base directory
+    pub fn load_session(&self, session_id: &str) -> Result<Session> {
+        self.read_session(session_id)
+    }
+
+    /// List all sessions under the base directory, most recently updated first.
+    /// Sessions that can't be read or decrypted are skipped with a warning.
+    pub fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
+        let mut sessions = Vec::new();
+        for session in self.stored_sessions()? {
+            let first_user_message = session
+                .get_messages()
+                .iter()
+                .find(|m| m.role == "user")
+                .map(|m| m.content.clone());
+
+            sessions.push(SessionInfo {
+                session_id: session.session_id().to_string(),
+                created_at: session.created_at(),
+                updated_at: session.updated_at(),
+                message_count: session.get_messages().len(),
+                first_user_message,
+            });
+        }
+        sessions.sort_by_key(|info| std::cmp::Reverse(info.updated_at));
+        Ok(sessions)
+    }
+
+    /// Case-insensitive full-text search over the messages of every session
+    pub fn search_sessions(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let query = query.to_lowercase();
+        let mut hits = Vec::new();
+        for session in self.stored_sessions()? {
+            for (index, message) in session.get_messages().iter().enumerate() {
+                if message.content.to_lowercase().contains(&query) {
+                    hits.push(SearchHit {
+                        session_id: session.session_id().to_string(),
+                        message_index: index,
+                        role: message.role.clone(),
+                        content: message.content.clone(),
+                    });
+                }
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Delete a session and everything stored in its directory
+    pub fn delete_session(&self, session_id: &str) -> Result<()> {
+        validate_session_id(session_id)?;
+        let path = self.directory.join(session_id);
+        if !path.is_dir() {
+            return Err(AxonerError::session_store(format!("Session not found: {}", session_id)));
+        }
+        fs::remove_dir_all(path).map_err(AxonerError::session_io)?;
+        Ok(())
+    }
+
+    /// Purge sessions that fall outside the retention policy and return their ids.
+    /// The session owned by this manager and sessions that can't be read are
+    /// never purged.
+    pub fn apply_retention(&self, policy: &RetentionPolicy) -> Result<Vec<String>> {
+        let sessions = self.list_sessions()?;
+        let cutoff = policy
+            .max_age
+            .and_then(|max_age| chrono::Duration::from_std(max_age).ok())
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age));
+
+        let mut purged = Vec::new();
+        for (rank, info) in sessions.iter().enumerate() {
+            if info.session_id == self.session_id {
+                continue;
+            }
+            let expired = cutoff.is_some_and(|cutoff| info.updated_at < cutoff);
+            let over_limit = policy.max_sessions.is_some_and(|max| rank >= max);
+            if expired || over_limit {
+                self.delete_session(&info.session_id)?;
+                purged.push(info.session_id.clone());
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Fork a stored session at message `at` into a new session and save both.
+    /// The new session becomes the active branch of its parent.
+    pub fn fork_session(&self, session_id: &str, at: usize, new_session_id: String) -> Result<Session> {
+        validate_session_id(&new_session_id)?;
+        if self.session_file(&new_session_id).exists() {
+            return Err(AxonerError::session_store(format!("Session {} already exists", new_session_id)));
+        }
+        let mut parent = self.load_session(session_id)?;
+        let fork = parent.fork(at, new_session_id)?;
+        self.write_session(&fork)?;
+        self.write_session(&parent)?;
+        Ok(fork)
+    }
+
+    /// Select which branch of a stored session the active path follows
+    pub fn set_active_branch(&self, session_id: &str, branch: Option<String>) -> Result<()> {
+        let mut session = self.load_session(session_id)?;
+        session.set_active_branch(branch)?;
+        self.write_session(&session)
+    }
+
+    /// Build the whole branch tree containing `session_id`, starting from its root
+    pub fn branch_tree(&self, session_id: &str) -> Result<BranchNode> {
+        let root = self.root_session(session_id)?;
+        let mut visited = vec![root.session_id().to_string()];
+        self.build_branch_node(&root, &mut visited)
+    }
+
+    /// Sessions from the root of `session_id`'s tree down through each active branch.
+    /// The last session holds the full conversation of the active path.
+    pub fn active_path(&self, session_id: &str) -> Result<Vec<Session>> {
+        let mut path = vec![self.root_session(session_id)?];
+        while let Some(next) = path.last().and_then(|s| s.active_branch()).map(str::to_string) {
+            if !self.session_file(&next).is_file() || path.iter().any(|s| s.session_id() == next) {
+                break;
+            }
+            path.push(self.load_session(&next)?);
+        }
+        Ok(path)
+    }
+
+    fn root_session(&self, session_id: &str) -> Result<Session> {
+        let mut session = self.load_session(session_id)?;
+        let mut visited = vec![session.session_id().to_string()];
+        while let Some(parent) = session.parent().map(|p| p.session_id.clone()) {
+            if !self.session_file(&parent).is_file() || visited.contains(&parent) {
+                break;
+            }
+            session = self.load_session(&parent)?;
+            visited.push(parent);
+        }
+        Ok(session)
+    }
+
+    /// `visited` holds every session already in the tree, so a corrupted
+    /// branch list that loops back can't recurse forever
+    fn build_branch_node(&self, session: &Session, visited: &mut Vec<String>) -> Result<BranchNode> {
+        let mut children = Vec::new();
+        for branch in session.branches() {
+            // Branches deleted from disk are left out of the tree
+            if self.session_file(branch).is_file() && !visited.contains(branch) {
+                visited.push(branch.clone());
+                children.push(self.build_branch_node(&self.load_session(branch)?, visited)?);
+            }
+        }
+        Ok(BranchNode {
+            session_id: session.session_id().to_string(),
+            fork_index: session.parent().map(|p| p.message_index),
+            active_branch: session.active_branch().map(str::to_string),
+            children,
+        })
+    }
+
+    fn session_file(&self, session_id: &str) -> PathBuf {
+        self.directory.join(session_id).join("messages.json")
+    }
+
+    fn write_session(&self, session: &Session) -> Result<()> {
+        let location = self.session_file(session.session_id());
+        if let Some(dir) = location.parent() {
+            fs::create_dir_all(dir).map_err(AxonerError::session_io)?;
+        }
+        let content = self.encode_session(session.session_id(), session)?;
+        fs::write(&location, content).map_err(AxonerError::session_io)?;
+        Ok(())
+    }
+
+    /// Re-encrypt every stored session not yet written with the current key,
+    /// including plaintext ones, and return their ids
+    pub fn rotate_encryption(&self) -> Result<Vec<String>> {
+        let encryption = self
+            .encryption
+            .as_ref()
+            .ok_or_else(|| AxonerError::session_store("Session encryption is not configured"))?;
+
+        let mut rotated = Vec::new();
+        for session_id in self.stored_session_ids()? {
+            let content = fs::read_to_string(self.session_file(&session_id)).map_err(AxonerError::session_io)?;
+            if SessionEncryption::key_id_of(&content).as_deref() == Some(encryption.current_key_id()) {
+                continue;
+            }
+            let session = self.decode_session(&session_id, &content, true)?;
+            let content = self.encode_session(&session_id, &session)?;
+            fs::write(self.session_file(&session_id), content).map_err(AxonerError::session_io)?;
+            rotated.push(session_id);
+        }
+        Ok(rotated)
+    }
+
+    fn encode_session(&self, session_id: &str, session: &Session) -> Result<String> {
+        let json = serde_json::to_string(session)?;
+        match &self.encryption {
+            Some(encryption) => encryption.encrypt(session_id, json.as_bytes()),
+            None => Ok(json),
+        }
+    }
+
+    fn read_session(&self, session_id: &str) -> Result<Session> {
+        let content = fs::read_to_string(self.session_file(session_id)).map_err(AxonerError::session_io)?;
+        self.decode_session(session_id, &content, self.plaintext_migration)
+    }
+
+    /// Parse a stored session. With encryption configured, plaintext is only
+    /// accepted when `allow_plaintext` is set.
+    fn decode_session(&self, session_id: &str, content: &str, allow_plaintext: bool) -> Result<Session> {
+        if SessionEncryption::key_id_of(content).is_none() {
+            if self.encryption.is_some() && !allow_plaintext {
+                return Err(AxonerError::session_store(format!(
+                    "Session {} is not encrypted; use rotate_encryption or with_plaintext_migration to migrate it",
+                    session_id
+                )));
+            }
+            return Ok(serde_json::from_str(content)?);
+        }
+        let encryption = self
+            .encryption
+            .as_ref()
+            .ok_or_else(|| {
+                AxonerError::session_store(format!("Session {} is encrypted but no key is configured", session_id))
+            })?;
+        Ok(serde_json::from_slice(&encryption.decrypt(session_id, content)?)?)
+    }
+
+    fn stored_session_ids(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.directory).map_err(AxonerError::session_io)? {
+            let entry = entry.map_err(AxonerError::session_io)?;
+            if entry.path().join("messages.json").is_file() {
+                ids.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Every readable session; one corrupt file shouldn't hide the others
+    fn stored_sessions(&self) -> Result<Vec<Session>> {
+        let mut sessions = Vec::new();
+        for id in self.stored_session_ids()? {
+            match self.read_session(&id) {
+                Ok(session) => sessions.push(session),
+                Err(e) => println!("⚠️ Skipping unreadable session {}: {}", id, e),
+            }
+        }
+        Ok(sessions)
+    }
+
+}
+
+fn validate_session_id(session_id: &str) -> Result<()> {
+    if session_id.is_empty() || Path::new(session_id).components().count() != 1 {
+        return Err(AxonerError::session_store(format!("Invalid session id: {:?}", session_id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::SessionKey;
+    use crate::provider::Message;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("axonerai-test-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn encryption() -> SessionEncryption {
+        SessionEncryption::new(SessionKey::new("k1", [7; 32]))
+    }
+
+    fn save_plaintext(dir: &Path, session_id: &str) {
+        let mut session = Session::new(session_id.to_string());
+        session.add_message(Message::user("hello"));
+        FileSessionManager::new(session_id.to_string(), dir.to_path_buf())
+            .unwrap()
+            .save(&session)
+            .unwrap();
+    }
+
+    #[test]
+    fn encrypted_manager_rejects_plaintext_files() {
+        let dir = temp_dir();
+        save_plaintext(&dir, "s1");
+
+        let manager = FileSessionManager::new("s1".to_string(), dir.clone()).unwrap().with_encryption(encryption());
+        assert!(manager.load().is_err());
+
+        let migrating = FileSessionManager::new("s1".to_string(), dir.clone())
+            .unwrap()
+            .with_encryption(encryption())
+            .with_plaintext_migration();
+        assert_eq!(migrating.load().unwrap().get_messages().len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_migrates_plaintext_sessions() {
+        let dir = temp_dir();
+        save_plaintext(&dir, "s1");
+
+        let manager = FileSessionManager::new("s1".to_string(), dir.clone()).unwrap().with_encryption(encryption());
+        assert_eq!(manager.rotate_encryption().unwrap(), vec!["s1".to_string()]);
+        assert_eq!(manager.load().unwrap().get_messages()[0].content, "hello");
+        assert!(manager.rotate_encryption().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_session_is_a_session_store_error() {
+        let dir = temp_dir();
+        let manager = FileSessionManager::new("s1".to_string(), dir.clone()).unwrap();
+        assert!(matches!(manager.load(), Err(AxonerError::SessionStore { source: Some(_), .. })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,13 +1,21 @@
-use crate::provider::{CompletionResponse, Message, Provider, StopReason, Tool, ToolCall};
-use anyhow::{anyhow, Result};
+use crate::context::context_limit;
+use crate::http::{endpoint, HttpConfig};
+use crate::openai::{openai_message, openai_tool_choice};
+use crate::tokens::{TiktokenCounter, TokenCounter};
+use crate::provider::{CompletionResponse, Message, Provider, ReasoningEffort, StopReason, Tool, ToolCall, ToolChoice, Usage};
+use crate::error::{check_response, AxonerError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+const DEFAULT_BASE_URL: &str = "https://api.groq.com/openai/v1";
+
 pub struct GroqProvider {
     api_key: String,
     model: String,
     client: reqwest::Client,
+    base_url: String,
+    reasoning_effort: Option<ReasoningEffort>,
 }
 
 impl GroqProvider {
@@ -16,6 +24,8 @@ impl GroqProvider {
             api_key,
             model: "openai/gpt-oss-20b".to_string(),
             client: reqwest::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            reasoning_effort: None,
         }
     }
 
@@ -23,6 +33,34 @@ impl GroqProvider {
         self.model = model;
         self
     }
+
+    /// How much reasoning models should do before answering
+    pub fn with_reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(effort);
+        self
+    }
+
+    /// Send requests through a caller-configured client, which may be shared
+    /// with other providers
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Replace the API base URL (default `https://api.groq.com/openai/v1`)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Build a client from `config`, and use its base URL if it sets one
+    pub fn with_http_config(mut self, config: &HttpConfig) -> Result<Self> {
+        self.client = config.build_client()?;
+        if let Some(ref base_url) = config.base_url {
+            self.base_url = base_url.clone();
+        }
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -32,7 +70,8 @@ impl Provider for GroqProvider {
         messages: Vec<Message>,
         tools: Option<Vec<Tool>>,
         max_tokens: Option<u32>,
-        system_prompt: Option<String>
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>
     ) -> Result<CompletionResponse> {
 
         let mut complete_message:Vec<Value> = Vec::new();
@@ -44,11 +83,20 @@ impl Provider for GroqProvider {
             }));
         }
 
+        // Reasoning kept from other providers is dropped
+        for message in &messages {
+            complete_message.push(openai_message(message, "Groq", false)?);
+        }
+
         let mut body = json!({
             "model": self.model,
-            "messages": messages,
+            "messages": complete_message,
         });
 
+        if let Some(effort) = self.reasoning_effort {
+            body["reasoning_effort"] = json!(effort);
+        }
+
         // let tool_clone = tools.clone();
 
         if let Some(max_tokens) = max_tokens {
@@ -71,12 +119,16 @@ impl Provider for GroqProvider {
                 })
                 .collect();
             body["tools"] = json!(groq_tools);
+
+            if let Some(ref choice) = tool_choice {
+                body["tool_choice"] = openai_tool_choice(choice);
+            }
         }
 
 
         let response = self
             .client
-            .post("https://api.groq.com/openai/v1/chat/completions")
+            .post(endpoint(&self.base_url, "chat/completions"))
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("content-type", "application/json")
             .json(&body)
@@ -84,11 +136,7 @@ impl Provider for GroqProvider {
             .await?;
 
         
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            return Err(anyhow!("Groq API error {}: {}", status, error_text));
-        }
+        let response = check_response("Groq", response).await?;
 
         let api_response: GroqResponse = response.json().await?;
         
@@ -98,7 +146,7 @@ impl Provider for GroqProvider {
         let choice = api_response
             .choices
             .first()
-            .ok_or_else(|| anyhow!("No choices in Groq response"))?;
+            .ok_or_else(|| AxonerError::invalid_response("Groq", "no choices in response"))?;
 
         let text = choice.message.content.clone();
 
@@ -119,26 +167,63 @@ impl Provider for GroqProvider {
             vec![]
         };
 
+        // Forced function calls finish with "stop", so tool calls decide first
         let stop_reason = match choice.finish_reason.as_str() {
+            _ if !tool_calls.is_empty() => StopReason::ToolUse,
             "tool_calls" => StopReason::ToolUse,
             "stop" => StopReason::EndTurn,
             "length" => StopReason::MaxTokens,
             "content_filter" => StopReason::ContentFilter,
-            _ => StopReason::Error,
+            other => StopReason::Other(other.to_string()),
         };
 
+        let usage = api_response
+            .usage
+            .map(|u| Usage {
+                input_tokens: u.prompt_tokens,
+                output_tokens: u.completion_tokens,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+
         Ok(CompletionResponse {
             text,
             tool_calls,
             stop_reason,
+            usage,
+            reasoning: choice.message.reasoning.clone(),
+            thinking: Vec::new(),
         })
     }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn fingerprint(&self) -> Value {
+        json!({ "base_url": self.base_url, "reasoning_effort": self.reasoning_effort })
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        context_limit(&self.model)
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        Box::new(TiktokenCounter::for_model(&self.model))
+    }
 }
 
 // Groq API response structures (OpenAI-compatible)
 #[derive(Debug, Deserialize, Serialize)]
 struct GroqResponse {
     choices: Vec<Choice>,
+    usage: Option<GroqUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GroqUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -150,6 +235,9 @@ struct Choice {
 #[derive(Debug, Deserialize, Serialize)]
 struct ResponseMessage {
     content: Option<String>,
+    /// Reasoning text, returned by some OpenAI-compatible servers
+    #[serde(default)]
+    reasoning: Option<String>,
     tool_calls: Option<Vec<GroqToolCall>>,
 }
 
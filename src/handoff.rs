@@ -0,0 +1,53 @@
+use crate::agent::Agent;
+use crate::provider::Tool;
+use crate::tool::sanitize_tool_name;
+use serde_json::json;
+
+/// A specialist agent the model can hand the conversation over to
+pub struct Handoff {
+    pub(crate) agent: Agent,
+    pub(crate) description: String,
+}
+
+impl Handoff {
+    pub fn new(agent: Agent, description: impl Into<String>) -> Self {
+        Self {
+            agent,
+            description: description.into(),
+        }
+    }
+
+    pub fn agent(&self) -> &Agent {
+        &self.agent
+    }
+
+    /// Name of the tool the model calls to hand over to this agent
+    pub fn tool_name(&self) -> String {
+        handoff_tool_name(self.agent.name())
+    }
+
+    /// Tool definition offered to the model alongside the agent's own tools
+    pub(crate) fn as_tool(&self) -> Tool {
+        Tool {
+            name: self.tool_name(),
+            description: format!(
+                "Hand the conversation over to the '{}' agent. {}",
+                self.agent.name(),
+                self.description
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "reason": {
+                        "type": "string",
+                        "description": "Why the conversation is being handed over"
+                    }
+                }
+            }),
+        }
+    }
+}
+
+fn handoff_tool_name(agent_name: &str) -> String {
+    format!("transfer_to_{}", sanitize_tool_name(agent_name))
+}
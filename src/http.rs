@@ -0,0 +1,98 @@
+use crate::error::{AxonerError, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+
+/// HTTP settings applied to a provider's client
+#[derive(Debug, Clone, Default)]
+pub struct HttpConfig {
+    pub connect_timeout: Option<Duration>,
+    /// Maximum time between reads of the response
+    pub read_timeout: Option<Duration>,
+    /// Maximum time for the whole request
+    pub timeout: Option<Duration>,
+    /// Proxy URL for all requests, e.g. `http://proxy.internal:8080`
+    pub proxy: Option<String>,
+    /// Headers sent with every request
+    pub headers: Vec<(String, String)>,
+    pub user_agent: Option<String>,
+    /// Replaces the provider's API base URL, e.g. for gateways or compatible servers.
+    /// For every provider this includes the API version path and ends before the
+    /// endpoint, e.g. `https://api.anthropic.com/v1` or `http://localhost:11434/v1`.
+    pub base_url: Option<String>,
+}
+
+impl HttpConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Build a client with these settings. The client can be shared between
+    /// providers with their `with_client` methods.
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let invalid = |e: &dyn std::fmt::Display| AxonerError::Other(anyhow::anyhow!("Invalid header {}: {}", name, e));
+            let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+            let header_value = HeaderValue::from_str(value).map_err(|e| invalid(&e))?;
+            headers.append(header_name, header_value);
+        }
+        Ok(builder.default_headers(headers).build()?)
+    }
+}
+
+/// Join a base URL and an endpoint path without doubling the slash
+pub(crate) fn endpoint(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+}
@@ -1,20 +1,52 @@
+pub mod error;
 pub mod provider;
+pub mod http;
 pub mod groq;
 pub mod openai;
 pub mod anthropic;
+pub mod text_tools;
+pub mod fallback;
+pub mod router;
+pub mod mock;
+pub mod cassette;
+pub mod cache;
+pub mod rate_limit;
 pub mod tool;
 pub mod tools;
 pub mod executor;
 pub mod agent;
+pub mod run_result;
+pub mod plan;
+pub mod handoff;
+pub mod workflow;
 pub mod session;
 pub mod file_session_manager;
+pub mod context;
+pub mod tokens;
+pub mod export;
+pub mod encryption;
 
 // Re-exporting main types for convenience
-pub use agent::Agent;
+pub use error::AxonerError;
+pub use agent::{Agent, CancelHandle};
+pub use handoff::Handoff;
+pub use workflow::Workflow;
+pub use run_result::{AgentRunResult, AgentStep, RunOutcome};
+pub use http::HttpConfig;
 pub use groq::GroqProvider;
 pub use openai::OpenAIProvider;
-pub use anthropic::AnthropicProvider;
-pub use tool::{Tool, ToolRegistry};
-pub use tools::{Calculator, WebSearch, WebScrape};
-pub use file_session_manager::FileSessionManager;
-pub use session::Session;
\ No newline at end of file
+pub use anthropic::{AnthropicProvider, CacheBreakpoint, PromptCaching};
+pub use text_tools::TextToolProvider;
+pub use fallback::{ErrorClass, FallbackProvider};
+pub use router::{RouteRequest, RouterProvider};
+pub use mock::{MockProvider, RecordedRequest};
+pub use cassette::CassetteProvider;
+pub use cache::{CacheBypass, CachingProvider, DiskCache, MemoryCache};
+pub use rate_limit::{RateLimitedProvider, RateLimiter};
+pub use tool::{Tool, ToolOutput, ToolRegistry};
+pub use tools::{AgentTool, Calculator, WebSearch, WebScrape};
+pub use file_session_manager::{BranchNode, FileSessionManager, RetentionPolicy, SearchHit, SessionInfo};
+pub use encryption::{SessionEncryption, SessionKey};
+pub use session::{ConversationSummary, ParentLink, RunState, Session};
+pub use context::ContextStrategy;
+pub use tokens::{ApproximateCounter, TiktokenCounter, TokenCounter};
\ No newline at end of file
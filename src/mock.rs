@@ -0,0 +1,200 @@
+use crate::error::{AxonerError, Result};
+use crate::provider::{CompletionResponse, Message, Provider, StopReason, Tool, ToolCall, ToolChoice, Usage};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// A request received by a `MockProvider`
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub messages: Vec<Message>,
+    pub tools: Option<Vec<Tool>>,
+    pub max_tokens: Option<u32>,
+    pub system_prompt: Option<String>,
+    pub tool_choice: Option<ToolChoice>,
+}
+
+impl RecordedRequest {
+    pub fn last_message(&self) -> Option<&Message> {
+        self.messages.last()
+    }
+
+    /// Names of the tools offered to the model
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.tools
+            .iter()
+            .flatten()
+            .map(|t| t.name.as_str())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    responses: Mutex<VecDeque<Result<CompletionResponse>>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+/// A provider that returns scripted responses in order and records every request,
+/// for testing agents and tools without API keys. Clones share the same script and
+/// recorded requests, so keep a clone to inspect after handing one to an `Agent`.
+#[derive(Clone, Default)]
+pub struct MockProvider {
+    state: Arc<MockState>,
+    context_limit: Option<u32>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a complete response
+    pub fn with_response(self, response: CompletionResponse) -> Self {
+        self.state.responses.lock().unwrap().push_back(Ok(response));
+        self
+    }
+
+    /// Queue a final text answer
+    pub fn with_text(self, text: impl Into<String>) -> Self {
+        self.with_stop(text, StopReason::EndTurn)
+    }
+
+    /// Queue a text response that stops for the given reason
+    pub fn with_stop(self, text: impl Into<String>, stop_reason: StopReason) -> Self {
+        self.with_response(CompletionResponse {
+            text: Some(text.into()),
+            tool_calls: Vec::new(),
+            stop_reason,
+            usage: Usage::default(),
+            reasoning: None,
+            thinking: Vec::new(),
+        })
+    }
+
+    /// Queue a request to call one tool
+    pub fn with_tool_call(self, name: &str, input: Value) -> Self {
+        self.with_tool_calls(vec![(name, input)])
+    }
+
+    /// Queue a request to call several tools in one turn
+    pub fn with_tool_calls(self, calls: Vec<(&str, Value)>) -> Self {
+        let queued = self.state.responses.lock().unwrap().len();
+        let tool_calls = calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, input))| ToolCall {
+                id: format!("mock_call_{}_{}", queued, i),
+                name: name.to_string(),
+                input,
+            })
+            .collect();
+        self.with_response(CompletionResponse {
+            text: None,
+            tool_calls,
+            stop_reason: StopReason::ToolUse,
+            usage: Usage::default(),
+            reasoning: None,
+            thinking: Vec::new(),
+        })
+    }
+
+    /// Queue an error, returned instead of a response
+    pub fn with_error(self, error: AxonerError) -> Self {
+        self.state.responses.lock().unwrap().push_back(Err(error));
+        self
+    }
+
+    /// Context window reported to context strategies
+    pub fn with_context_limit(mut self, limit: u32) -> Self {
+        self.context_limit = Some(limit);
+        self
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    pub fn last_request(&self) -> Option<RecordedRequest> {
+        self.state.requests.lock().unwrap().last().cloned()
+    }
+
+    /// Number of scripted responses not returned yet
+    pub fn remaining(&self) -> usize {
+        self.state.responses.lock().unwrap().len()
+    }
+
+    #[track_caller]
+    pub fn assert_request_count(&self, expected: usize) {
+        let count = self.state.requests.lock().unwrap().len();
+        assert_eq!(count, expected, "MockProvider received {} requests, expected {}", count, expected);
+    }
+
+    /// Assert every scripted response was used
+    #[track_caller]
+    pub fn assert_all_consumed(&self) {
+        let remaining = self.remaining();
+        assert_eq!(remaining, 0, "MockProvider has {} unused scripted responses", remaining);
+    }
+
+    /// Assert the last message of the most recent request contains `text`
+    #[track_caller]
+    pub fn assert_last_message_contains(&self, text: &str) {
+        let request = self.last_request().expect("MockProvider received no requests");
+        let content = request.last_message().map(|m| m.content.as_str()).unwrap_or_default();
+        assert!(content.contains(text), "Last message {:?} does not contain {:?}", content, text);
+    }
+
+    /// Assert the most recent request offered the named tool
+    #[track_caller]
+    pub fn assert_tool_offered(&self, name: &str) {
+        let request = self.last_request().expect("MockProvider received no requests");
+        let names = request.tool_names();
+        assert!(names.contains(&name), "Tool {:?} was not offered, tools were {:?}", name, names);
+    }
+
+    /// Assert the most recent request's system prompt contains `text`
+    #[track_caller]
+    pub fn assert_system_prompt_contains(&self, text: &str) {
+        let request = self.last_request().expect("MockProvider received no requests");
+        let prompt = request.system_prompt.unwrap_or_default();
+        assert!(prompt.contains(text), "System prompt {:?} does not contain {:?}", prompt, text);
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let mut requests = self.state.requests.lock().unwrap();
+        requests.push(RecordedRequest {
+            messages,
+            tools,
+            max_tokens,
+            system_prompt,
+            tool_choice,
+        });
+        let number = requests.len();
+        drop(requests);
+
+        self.state
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| Err(anyhow!("MockProvider has no scripted response for request {}", number).into()))
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        self.context_limit
+    }
+}
@@ -1,13 +1,21 @@
-use crate::provider::{CompletionResponse, Message, Provider, StopReason, Tool, ToolCall};
-use anyhow::{anyhow, Result};
+use crate::context::context_limit;
+use crate::http::{endpoint, HttpConfig};
+use crate::tokens::{TiktokenCounter, TokenCounter};
+use crate::provider::{CompletionResponse, ContentPart, MediaSource, Message, Provider, ReasoningEffort, StopReason, Tool, ToolCall, ToolChoice, Usage};
+use crate::error::{check_response, AxonerError, Result};
+use anyhow::anyhow;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
 pub struct OpenAIProvider {
     api_key: String,
     model: String,
     client: reqwest::Client,
+    base_url: String,
+    reasoning_effort: Option<ReasoningEffort>,
 }
 
 impl OpenAIProvider {
@@ -16,6 +24,8 @@ impl OpenAIProvider {
             api_key,
             model: "gpt-5-mini".to_string(),
             client: reqwest::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            reasoning_effort: None,
         }
     }
 
@@ -23,6 +33,34 @@ impl OpenAIProvider {
         self.model = model;
         self
     }
+
+    /// How much reasoning models should do before answering
+    pub fn with_reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(effort);
+        self
+    }
+
+    /// Send requests through a caller-configured client, which may be shared
+    /// with other providers
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Replace the API base URL (default `https://api.openai.com/v1`)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Build a client from `config`, and use its base URL if it sets one
+    pub fn with_http_config(mut self, config: &HttpConfig) -> Result<Self> {
+        self.client = config.build_client()?;
+        if let Some(ref base_url) = config.base_url {
+            self.base_url = base_url.clone();
+        }
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -32,7 +70,8 @@ impl Provider for OpenAIProvider {
         messages: Vec<Message>,
         tools: Option<Vec<Tool>>,
         max_completion_tokens: Option<u32>,
-        system_prompt: Option<String>
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>
     ) -> Result<CompletionResponse> {
 
         let mut complete_message:Vec<Value> = Vec::new();
@@ -44,11 +83,20 @@ impl Provider for OpenAIProvider {
             }));
         }
 
+        // Reasoning kept from other providers is dropped
+        for message in &messages {
+            complete_message.push(openai_message(message, "OpenAI", true)?);
+        }
+
         let mut body = json!({
             "model": self.model,
-            "messages": messages,
+            "messages": complete_message,
         });
 
+        if let Some(effort) = self.reasoning_effort {
+            body["reasoning_effort"] = json!(effort);
+        }
+
         if let Some(max_completion_tokens) = max_completion_tokens {
             body["max_completion_tokens"] = json!(max_completion_tokens);
         }
@@ -69,13 +117,17 @@ impl Provider for OpenAIProvider {
                 })
                 .collect();
             body["tools"] = json!(openai_tools);
+
+            if let Some(ref choice) = tool_choice {
+                body["tool_choice"] = openai_tool_choice(choice);
+            }
         }
 
 
 
         let response = self
             .client
-            .post("https://api.openai.com/v1/chat/completions")
+            .post(endpoint(&self.base_url, "chat/completions"))
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-type", "application/json")
             .json(&body)
@@ -83,11 +135,7 @@ impl Provider for OpenAIProvider {
             .await?;
 
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            return Err(anyhow!("OpenAI API error {}: {}", status, error_text));
-        }
+        let response = check_response("OpenAI", response).await?;
 
         let api_response: OpenAIResponse = response.json().await?;
 
@@ -95,7 +143,7 @@ impl Provider for OpenAIProvider {
         let choice = api_response
             .choices
             .first()
-            .ok_or_else(|| anyhow!("No choices in OpenAI response"))?;
+            .ok_or_else(|| AxonerError::invalid_response("OpenAI", "no choices in response"))?;
 
         let text = choice.message.content.clone();
 
@@ -116,28 +164,124 @@ impl Provider for OpenAIProvider {
             vec![]
         };
 
+        // Forced function calls finish with "stop", so tool calls decide first
         let stop_reason = match choice.finish_reason.as_str() {
+            _ if !tool_calls.is_empty() => StopReason::ToolUse,
             "tool_calls" => StopReason::ToolUse,
             "stop" => StopReason::EndTurn,
             "length" => StopReason::MaxTokens,
             "content_filter" => StopReason::ContentFilter,
-            _ => StopReason::Error,
+            other => StopReason::Other(other.to_string()),
         };
 
         println!("DEBUG: stop_reason: {:?}\n", &stop_reason);
 
+        let usage = api_response
+            .usage
+            .map(|u| Usage {
+                input_tokens: u.prompt_tokens,
+                output_tokens: u.completion_tokens,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+
         Ok(CompletionResponse {
             text,
             tool_calls,
             stop_reason,
+            usage,
+            reasoning: choice.message.reasoning.clone(),
+            thinking: Vec::new(),
         })
     }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn fingerprint(&self) -> Value {
+        json!({ "base_url": self.base_url, "reasoning_effort": self.reasoning_effort })
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        context_limit(&self.model)
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        Box::new(TiktokenCounter::for_model(&self.model))
+    }
+}
+
+/// A message in the chat completions format. Messages with images or documents
+/// use content part arrays. Text documents become text parts; other documents
+/// are only sent where `documents` is set, and only as base64 data.
+pub(crate) fn openai_message(message: &Message, provider: &str, documents: bool) -> Result<Value> {
+    if message.parts.is_empty() {
+        return Ok(json!({ "role": message.role, "content": message.content }));
+    }
+    let mut content = Vec::new();
+    if !message.content.is_empty() {
+        content.push(json!({ "type": "text", "text": message.content }));
+    }
+    for part in &message.parts {
+        content.push(match part {
+            ContentPart::Image { source } => {
+                let url = match source {
+                    MediaSource::Url { url } => url.clone(),
+                    MediaSource::Base64 { media_type, data } => format!("data:{};base64,{}", media_type, data),
+                    MediaSource::Text { .. } => return Err(anyhow!("Images can't have a text source").into()),
+                };
+                json!({ "type": "image_url", "image_url": { "url": url } })
+            }
+            ContentPart::Document {
+                source: MediaSource::Text { data, .. },
+                title,
+            } => match title {
+                Some(title) => json!({ "type": "text", "text": format!("{}:\n{}", title, data) }),
+                None => json!({ "type": "text", "text": data }),
+            },
+            ContentPart::Document {
+                source: MediaSource::Base64 { media_type, data },
+                title,
+            } if documents => json!({
+                "type": "file",
+                "file": {
+                    "filename": title.as_deref().unwrap_or("document"),
+                    "file_data": format!("data:{};base64,{}", media_type, data),
+                }
+            }),
+            ContentPart::Document { .. } if documents => {
+                return Err(anyhow!("{} only accepts documents as base64 data", provider).into());
+            }
+            ContentPart::Document { .. } => {
+                return Err(anyhow!("{} does not accept document content", provider).into());
+            }
+        });
+    }
+    Ok(json!({ "role": message.role, "content": content }))
+}
+
+/// `tool_choice` in the chat completions format, shared by compatible APIs
+pub(crate) fn openai_tool_choice(choice: &ToolChoice) -> Value {
+    match choice {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool(name) => json!({ "type": "function", "function": { "name": name } }),
+    }
 }
 
 // OpenAI API response structures
 #[derive(Debug, Deserialize, Serialize)]
 struct OpenAIResponse {
     choices: Vec<Choice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OpenAIUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -149,6 +293,9 @@ struct Choice {
 #[derive(Debug, Deserialize, Serialize)]
 struct ResponseMessage {
     content: Option<String>,
+    /// Reasoning text, returned by some OpenAI-compatible servers
+    #[serde(default)]
+    reasoning: Option<String>,
     tool_calls: Option<Vec<OpenAIToolCall>>,
 }
 
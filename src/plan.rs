@@ -0,0 +1,203 @@
+use crate::provider::Tool;
+use crate::run_result::RunOutcome;
+use serde::Deserialize;
+
+/// Progress of one plan step
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanStepStatus {
+    Pending,
+    /// The step's tool loop finished with an answer
+    Completed,
+    /// The step's tool loop stopped without finishing
+    Failed,
+    /// The run ended before the step was attempted
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanStep {
+    pub description: String,
+    pub status: PlanStepStatus,
+    /// What the agent reported after carrying out the step
+    pub result: Option<String>,
+    /// How the step's tool loop ended
+    pub outcome: Option<RunOutcome>,
+}
+
+/// The plan of a plan-and-execute run. Revisions only replace pending steps,
+/// so attempted steps keep their results.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+    /// Number of times the model changed the plan after a step
+    pub revisions: usize,
+}
+
+impl Plan {
+    pub(crate) fn new(descriptions: Vec<String>) -> Self {
+        let mut plan = Plan::default();
+        plan.push_pending(descriptions);
+        plan
+    }
+
+    /// Steps not attempted yet
+    pub fn pending(&self) -> impl Iterator<Item = &PlanStep> {
+        self.steps.iter().filter(|s| s.status == PlanStepStatus::Pending)
+    }
+
+    pub(crate) fn next_pending(&self) -> Option<usize> {
+        self.steps.iter().position(|s| s.status == PlanStepStatus::Pending)
+    }
+
+    pub(crate) fn pending_descriptions(&self) -> Vec<String> {
+        self.pending().map(|s| s.description.clone()).collect()
+    }
+
+    pub(crate) fn record(&mut self, index: usize, result: Option<String>, outcome: RunOutcome) {
+        let step = &mut self.steps[index];
+        step.status = if outcome == RunOutcome::Completed {
+            PlanStepStatus::Completed
+        } else {
+            PlanStepStatus::Failed
+        };
+        step.result = result;
+        step.outcome = Some(outcome);
+    }
+
+    /// Replace the pending steps with the model's revised list. Returns whether
+    /// the plan changed.
+    pub(crate) fn revise(&mut self, remaining: Vec<String>) -> bool {
+        if self.pending_descriptions() == remaining {
+            return false;
+        }
+        self.steps.retain(|s| s.status != PlanStepStatus::Pending);
+        self.push_pending(remaining);
+        self.revisions += 1;
+        true
+    }
+
+    pub(crate) fn skip_pending(&mut self) {
+        for step in &mut self.steps {
+            if step.status == PlanStepStatus::Pending {
+                step.status = PlanStepStatus::Skipped;
+            }
+        }
+    }
+
+    fn push_pending(&mut self, descriptions: Vec<String>) {
+        self.steps.extend(descriptions.into_iter().map(|description| PlanStep {
+            description,
+            status: PlanStepStatus::Pending,
+            result: None,
+            outcome: None,
+        }));
+    }
+
+    /// Numbered steps with their status and results, for the planner prompts
+    fn describe(&self) -> String {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let mut line = format!("{}. [{:?}] {}", i + 1, step.status, step.description);
+                if let Some(ref result) = step.result {
+                    line.push_str(&format!("\n   Result: {}", result));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Deserialize)]
+struct PlanResponse {
+    steps: Vec<String>,
+}
+
+/// Read the `{"steps": [...]}` object from a planner reply, ignoring any text
+/// around it. `None` if the reply has no such object.
+pub(crate) fn parse_steps(text: &str) -> Option<Vec<String>> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let response: PlanResponse = serde_json::from_str(text.get(start..=end)?).ok()?;
+    Some(
+        response
+            .steps
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+    )
+}
+
+const FORMAT: &str = "Reply with only a JSON object of the form {\"steps\": [\"first step\", \"second step\"]}.";
+
+pub(crate) fn planning_prompt(task: &str, tools: &[Tool]) -> String {
+    let tools = tools
+        .iter()
+        .map(|t| format!("- {}: {}", t.name, t.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Break the following task into a short list of concrete steps. Each step will be \
+carried out separately, with these tools available:\n{}\n\nTask: {}\n\n{}",
+        if tools.is_empty() { "(none)".to_string() } else { tools },
+        task,
+        FORMAT
+    )
+}
+
+pub(crate) fn revision_prompt(task: &str, plan: &Plan) -> String {
+    format!(
+        "Task: {}\n\nPlan so far:\n{}\n\nBased on the results so far, list the steps that still need \
+to be done, changing, adding or dropping steps as needed. Use an empty list if the task can now be \
+answered. {}",
+        task,
+        plan.describe(),
+        FORMAT
+    )
+}
+
+pub(crate) fn step_prompt(task: &str, plan: &Plan, index: usize) -> String {
+    format!(
+        "You are working through a plan for this task: {}\n\nPlan:\n{}\n\nCarry out step {} now: {}\n\
+Only do this step, then report what you found.",
+        task,
+        plan.describe(),
+        index + 1,
+        plan.steps[index].description
+    )
+}
+
+pub(crate) fn answer_prompt(task: &str, plan: &Plan) -> String {
+    format!(
+        "Work on the plan is finished:\n{}\n\nUsing these results, give the final answer to the task: {}",
+        plan.describe(),
+        task
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_steps_reads_json_object() {
+        let steps = parse_steps(r#"{"steps": ["search", " summarize "]}"#).unwrap();
+        assert_eq!(steps, vec!["search", "summarize"]);
+    }
+
+    #[test]
+    fn parse_steps_ignores_surrounding_text_and_blank_steps() {
+        let reply = "Here is the plan:\n```json\n{\"steps\": [\"a\", \"\", \"b\"]}\n```\nGood luck!";
+        assert_eq!(parse_steps(reply).unwrap(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn parse_steps_rejects_replies_without_a_plan() {
+        assert_eq!(parse_steps("I will just answer directly."), None);
+        assert_eq!(parse_steps(r#"{"plan": ["a"]}"#), None);
+        assert_eq!(parse_steps("{not json}"), None);
+    }
+}
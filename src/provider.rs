@@ -1,7 +1,12 @@
+use anyhow::anyhow;
 use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use anyhow::Result;
+use std::path::Path;
+use crate::error::Result;
+use crate::tokens::{ApproximateCounter, TokenCounter};
 
 /// Core trait that all LLM providers must implement
 #[async_trait]
@@ -12,15 +17,185 @@ pub trait Provider: Send + Sync {
         messages: Vec<Message>,
         tools: Option<Vec<Tool>>,
         max_tokens: Option<u32>,
-        system_prompt: Option<String>
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>
     ) -> Result<CompletionResponse>;
+
+    /// Name of the configured model, if the provider has one
+    fn model(&self) -> Option<&str> {
+        None
+    }
+
+    /// Settings besides the model that change responses, such as the base URL
+    /// or reasoning options. Caches use it to keep responses of differently
+    /// configured providers apart.
+    fn fingerprint(&self) -> Value {
+        Value::Null
+    }
+
+    /// Context window size of the configured model in tokens, if known
+    fn context_limit(&self) -> Option<u32> {
+        None
+    }
+
+    /// Local token counter matching the configured model's tokenizer
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        Box::new(ApproximateCounter::default())
+    }
 }
 
 /// A message in the conversation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,      // "user" or "assistant"
     pub content: String,
+    /// Images and documents sent along with the text
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<ContentPart>,
+    /// Signed reasoning the model produced before this message, sent back on
+    /// later turns by providers that require it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thinking: Vec<ThinkingBlock>,
+}
+
+impl Message {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            parts: Vec::new(),
+            thinking: Vec::new(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content)
+    }
+
+    /// Attach an image or document
+    pub fn with_part(mut self, part: ContentPart) -> Self {
+        self.parts.push(part);
+        self
+    }
+}
+
+/// Non-text content of a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Image { source: MediaSource },
+    /// A document such as a PDF, for models that read them
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+}
+
+/// Where the bytes of an image or document come from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+    /// Plain text, for text documents
+    Text { media_type: String, data: String },
+}
+
+impl ContentPart {
+    /// An image given as base64 data, e.g. `image_base64("image/png", data)`
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::Image {
+            source: MediaSource::Base64 { media_type: media_type.into(), data: data.into() },
+        }
+    }
+
+    pub fn image_url(url: impl Into<String>) -> Self {
+        Self::Image {
+            source: MediaSource::Url { url: url.into() },
+        }
+    }
+
+    /// A document given as base64 data, e.g. `document_base64("application/pdf", data)`
+    pub fn document_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::Document {
+            source: MediaSource::Base64 { media_type: media_type.into(), data: data.into() },
+            title: None,
+        }
+    }
+
+    pub fn document_url(url: impl Into<String>) -> Self {
+        Self::Document {
+            source: MediaSource::Url { url: url.into() },
+            title: None,
+        }
+    }
+
+    /// A plain text document
+    pub fn document_text(text: impl Into<String>) -> Self {
+        Self::Document {
+            source: MediaSource::Text { media_type: "text/plain".to_string(), data: text.into() },
+            title: None,
+        }
+    }
+
+    /// Read an image or document from disk, with the media type taken from the
+    /// file extension (png, jpg, jpeg, gif, webp, pdf, txt)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        let media_type = match extension.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "pdf" => "application/pdf",
+            "txt" => "text/plain",
+            _ => return Err(anyhow!("Unsupported file type: {}", path.display()).into()),
+        };
+        let bytes = std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let title = path.file_name().map(|name| name.to_string_lossy().into_owned());
+
+        // Text documents are sent as text; only binary formats are base64 encoded
+        let source = if media_type == "text/plain" {
+            let data = String::from_utf8(bytes).map_err(|_| anyhow!("{} is not valid UTF-8", path.display()))?;
+            MediaSource::Text { media_type: media_type.to_string(), data }
+        } else {
+            MediaSource::Base64 { media_type: media_type.to_string(), data: STANDARD.encode(bytes) }
+        };
+        Ok(if media_type.starts_with("image/") {
+            Self::Image { source }
+        } else {
+            Self::Document { source, title }
+        })
+    }
+}
+
+/// A block of model reasoning, in Anthropic's content block format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThinkingBlock {
+    Thinking { thinking: String, signature: String },
+    /// Reasoning encrypted by the provider, returned as-is
+    RedactedThinking { data: String },
+}
+
+/// How much reasoning OpenAI-style reasoning models do before answering
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,12 +205,70 @@ pub struct Tool {
     pub input_schema: Value,
 }
 
+/// Whether the model may or must call tools. `None` on a request leaves the
+/// choice to the provider's default, which is `Auto` when tools are offered.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools
+    #[default]
+    Auto,
+    /// The model must answer without calling tools
+    None,
+    /// The model must call at least one tool
+    Required,
+    /// The model must call the named tool
+    Tool(String),
+}
+
+impl ToolChoice {
+    pub fn tool(name: impl Into<String>) -> Self {
+        Self::Tool(name.into())
+    }
+}
+
 /// Unified response from any LLM provider
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionResponse {
     pub text: Option<String>,
     pub tool_calls: Vec<ToolCall>,
     pub stop_reason: StopReason,
+    pub usage: Usage,
+    /// The model's reasoning, kept apart from the answer
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    /// Signed thinking blocks to keep with the assistant message
+    #[serde(default)]
+    pub thinking: Vec<ThinkingBlock>,
+}
+
+/// Tokens billed for a completion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Input tokens written to the provider's prompt cache
+    #[serde(default)]
+    pub cache_creation_input_tokens: u32,
+    /// Input tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: u32,
+}
+
+impl Usage {
+    /// All tokens processed, including cached input
+    pub fn total(&self) -> u32 {
+        self.input_tokens + self.output_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
 }
 
 /// When the model wants to call a tool
@@ -47,11 +280,13 @@ pub struct ToolCall {
 }
 
 /// Why the completion stopped
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StopReason {
     EndTurn,        // Natural completion
     ToolUse,        // Wants to call tools
     MaxTokens,      // Hit token limit
     ContentFilter,  // Filtered by provider
     Error,          // Something went wrong
+    /// A stop reason the crate doesn't handle, as reported by the provider
+    Other(String),
 }
\ No newline at end of file
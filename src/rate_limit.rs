@@ -0,0 +1,234 @@
+use crate::error::Result;
+use anyhow::anyhow;
+use crate::provider::{CompletionResponse, Message, Provider, Tool, ToolChoice};
+use crate::tokens::TokenCounter;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Token bucket refilled continuously up to its per-minute capacity
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// Take `amount` and return what was taken, or how long to wait for it.
+    /// Amounts above the capacity only wait for, and take, a full bucket.
+    fn try_take(&mut self, amount: f64) -> Result<f64, Duration> {
+        self.refill();
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            self.available -= amount;
+            return Ok(amount);
+        }
+        Err(Duration::from_secs_f64((amount - self.available) * 60.0 / self.capacity))
+    }
+
+    /// Correct an earlier estimate; the balance may go negative to repay usage
+    fn adjust(&mut self, amount: f64) {
+        self.available = (self.available + amount).min(self.capacity);
+    }
+}
+
+/// Wait until `amount` can be taken and return the amount taken
+async fn take(bucket: &Mutex<Bucket>, amount: f64) -> f64 {
+    loop {
+        let taken = bucket.lock().unwrap().try_take(amount);
+        match taken {
+            Ok(taken) => return taken,
+            Err(wait) => tokio::time::sleep(wait).await,
+        }
+    }
+}
+
+/// Requests-per-minute, tokens-per-minute and in-flight limits. Clones share the
+/// same budgets, so one limiter can coordinate every agent using an API key.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    requests: Option<Arc<Mutex<Bucket>>>,
+    tokens: Option<Arc<Mutex<Bucket>>>,
+    in_flight: Option<Arc<Semaphore>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit requests per minute. Fails for 0, which would allow no requests.
+    pub fn with_requests_per_minute(mut self, requests: u32) -> Result<Self> {
+        if requests == 0 {
+            return Err(anyhow!("requests per minute must be at least 1").into());
+        }
+        self.requests = Some(Arc::new(Mutex::new(Bucket::new(requests))));
+        Ok(self)
+    }
+
+    /// Limit input plus requested output tokens per minute. Input tokens are
+    /// estimated with the provider's token counter and corrected from the
+    /// reported usage once the response arrives. Fails for 0.
+    pub fn with_tokens_per_minute(mut self, tokens: u32) -> Result<Self> {
+        if tokens == 0 {
+            return Err(anyhow!("tokens per minute must be at least 1").into());
+        }
+        self.tokens = Some(Arc::new(Mutex::new(Bucket::new(tokens))));
+        Ok(self)
+    }
+
+    /// Maximum number of requests waiting for a response at the same time.
+    /// Fails for 0, which would block every request forever.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Result<Self> {
+        if max_in_flight == 0 {
+            return Err(anyhow!("max_in_flight must be at least 1").into());
+        }
+        self.in_flight = Some(Arc::new(Semaphore::new(max_in_flight)));
+        Ok(self)
+    }
+}
+
+/// Waits for room in a shared `RateLimiter` before every request
+pub struct RateLimitedProvider {
+    inner: Box<dyn Provider>,
+    limiter: RateLimiter,
+}
+
+impl RateLimitedProvider {
+    pub fn new(inner: Box<dyn Provider>, limiter: RateLimiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimitedProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let mut taken = 0.0;
+        if let Some(ref tokens) = self.limiter.tokens {
+            let counter = self.inner.token_counter();
+            let estimate = (counter.count_messages(&messages)
+                + system_prompt.as_deref().map(|p| counter.count_text(p)).unwrap_or(0)
+                + tools.as_deref().map(|t| counter.count_tools(t)).unwrap_or(0)
+                + max_tokens.unwrap_or(0) as usize) as f64;
+            taken = take(tokens, estimate).await;
+        }
+        if let Some(ref requests) = self.limiter.requests {
+            take(requests, 1.0).await;
+        }
+        let _permit = match self.limiter.in_flight {
+            Some(ref semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+
+        let response = self.inner.complete(messages, tools, max_tokens, system_prompt, tool_choice).await;
+        if let Some(ref tokens) = self.limiter.tokens {
+            match response {
+                // Settle the amount actually taken against the reported usage
+                Ok(ref response) if response.usage.total() > 0 => {
+                    tokens.lock().unwrap().adjust(taken - response.usage.total() as f64)
+                }
+                Ok(_) => {}
+                // Failed requests give their tokens back
+                Err(_) => tokens.lock().unwrap().adjust(taken),
+            }
+        }
+        response
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.inner.model()
+    }
+
+    fn fingerprint(&self) -> Value {
+        self.inner.fingerprint()
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        self.inner.context_limit()
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        self.inner.token_counter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_until_empty_then_asks_to_wait() {
+        let mut bucket = Bucket::new(60);
+        assert_eq!(bucket.try_take(40.0), Ok(40.0));
+        let wait = bucket.try_take(30.0).unwrap_err();
+        // 10 tokens short at one token per second
+        assert!(wait > Duration::from_secs_f64(9.9) && wait <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn amounts_above_capacity_take_a_full_bucket() {
+        let mut bucket = Bucket::new(100);
+        assert_eq!(bucket.try_take(250.0), Ok(100.0));
+        assert!(bucket.try_take(1.0).is_err());
+    }
+
+    #[test]
+    fn adjust_refunds_up_to_capacity_and_can_go_negative() {
+        let mut bucket = Bucket::new(100);
+        bucket.try_take(100.0).unwrap();
+        bucket.adjust(-50.0);
+        assert!(bucket.available < -49.0);
+        bucket.adjust(500.0);
+        assert_eq!(bucket.available, 100.0);
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let mut bucket = Bucket::new(60);
+        bucket.try_take(60.0).unwrap();
+        bucket.refilled_at -= Duration::from_secs(30);
+        assert_eq!(bucket.try_take(29.0), Ok(29.0));
+    }
+
+    #[test]
+    fn zero_requests_per_minute_is_rejected() {
+        assert!(RateLimiter::new().with_requests_per_minute(0).is_err());
+        assert!(RateLimiter::new().with_requests_per_minute(1).is_ok());
+    }
+
+    #[test]
+    fn zero_tokens_per_minute_is_rejected() {
+        assert!(RateLimiter::new().with_tokens_per_minute(0).is_err());
+        assert!(RateLimiter::new().with_tokens_per_minute(1).is_ok());
+    }
+
+    #[test]
+    fn zero_in_flight_is_rejected() {
+        assert!(RateLimiter::new().with_max_in_flight(0).is_err());
+        assert!(RateLimiter::new().with_max_in_flight(1).is_ok());
+    }
+}
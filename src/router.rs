@@ -0,0 +1,96 @@
+use crate::error::Result;
+use crate::provider::{CompletionResponse, Message, Provider, Tool, ToolChoice};
+use crate::tokens::{ApproximateCounter, TokenCounter};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// What a routing decision can look at
+pub struct RouteRequest<'a> {
+    pub messages: &'a [Message],
+    pub tools: Option<&'a [Tool]>,
+    pub system_prompt: Option<&'a str>,
+    /// Size of messages, system prompt and tools, estimated without a tokenizer
+    pub estimated_tokens: usize,
+}
+
+impl RouteRequest<'_> {
+    pub fn has_tools(&self) -> bool {
+        self.tools.is_some_and(|tools| !tools.is_empty())
+    }
+}
+
+/// Sends each request to one of several providers, chosen per request
+pub struct RouterProvider {
+    providers: Vec<Box<dyn Provider>>,
+    route: Box<dyn Fn(&RouteRequest) -> usize + Send + Sync>,
+}
+
+impl RouterProvider {
+    /// Send each request to the provider at the index returned by `route`
+    pub fn new(
+        providers: Vec<Box<dyn Provider>>,
+        route: impl Fn(&RouteRequest) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            providers,
+            route: Box::new(route),
+        }
+    }
+
+    /// Use `large` for prompts over `max_tokens` estimated tokens and `small` otherwise
+    pub fn by_prompt_size(small: Box<dyn Provider>, large: Box<dyn Provider>, max_tokens: usize) -> Self {
+        Self::new(vec![small, large], move |request| usize::from(request.estimated_tokens > max_tokens))
+    }
+
+    /// Use `with_tools` for requests that offer tools and `without_tools` otherwise
+    pub fn by_tools(with_tools: Box<dyn Provider>, without_tools: Box<dyn Provider>) -> Self {
+        Self::new(vec![with_tools, without_tools], |request| usize::from(!request.has_tools()))
+    }
+}
+
+#[async_trait]
+impl Provider for RouterProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let counter = ApproximateCounter::default();
+        let estimated_tokens = counter.count_messages(&messages)
+            + system_prompt.as_deref().map(|p| counter.count_text(p)).unwrap_or(0)
+            + tools.as_deref().map(|t| counter.count_tools(t)).unwrap_or(0);
+        let index = (self.route)(&RouteRequest {
+            messages: &messages,
+            tools: tools.as_deref(),
+            system_prompt: system_prompt.as_deref(),
+            estimated_tokens,
+        });
+
+        let provider = self.providers.get(index).ok_or_else(|| {
+            anyhow!("Router chose provider {} but only {} are configured", index, self.providers.len())
+        })?;
+        provider.complete(messages, tools, max_tokens, system_prompt, tool_choice).await
+    }
+
+    /// Every provider's settings, since any of them may serve a request
+    fn fingerprint(&self) -> Value {
+        Value::Array(self.providers.iter().map(|p| json!([p.model(), p.fingerprint()])).collect())
+    }
+
+    /// The largest known context window, since large prompts are expected to be
+    /// routed to a provider that fits them
+    fn context_limit(&self) -> Option<u32> {
+        self.providers.iter().filter_map(|p| p.context_limit()).max()
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        match self.providers.first() {
+            Some(provider) => provider.token_counter(),
+            None => Box::new(ApproximateCounter::default()),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::plan::Plan;
+use crate::provider::{StopReason, ToolCall, Usage};
+use std::fmt;
+use std::time::Duration;
+
+/// How an agent run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunOutcome {
+    /// The model finished its answer
+    Completed,
+    /// The run used up `max_iterations` without a final answer
+    MaxIterations,
+    /// The model hit its output token limit
+    MaxTokens,
+    /// The provider filtered the response
+    ContentFiltered,
+    /// The run used more tokens than the agent's token budget
+    BudgetExceeded,
+    /// The run was stopped through a `CancelHandle`
+    Cancelled,
+}
+
+/// One step of an agent run, in the order it happened
+#[derive(Debug, Clone)]
+pub enum AgentStep {
+    /// A call to the LLM
+    LlmTurn {
+        iteration: usize,
+        text: Option<String>,
+        /// Reasoning the model reported separately from its text
+        reasoning: Option<String>,
+        tool_calls: Vec<ToolCall>,
+        stop_reason: StopReason,
+        usage: Usage,
+        duration: Duration,
+    },
+    /// A tool executed on the model's request
+    ToolCall {
+        iteration: usize,
+        call: ToolCall,
+        result: String,
+        duration: Duration,
+    },
+    /// Control passed from one agent to another
+    Handoff {
+        iteration: usize,
+        from: String,
+        to: String,
+        reason: Option<String>,
+    },
+    /// The steps still to do, when a plan was made (revision 0) or revised
+    Planned {
+        revision: usize,
+        steps: Vec<String>,
+    },
+}
+
+/// Everything an agent run produced
+#[derive(Debug, Clone)]
+pub struct AgentRunResult {
+    /// The model's final answer, if it gave one
+    pub text: Option<String>,
+    pub outcome: RunOutcome,
+    /// Name of the agent that produced the outcome, after any handoffs
+    pub answered_by: String,
+    pub steps: Vec<AgentStep>,
+    /// Tokens used across all LLM turns of the run
+    pub usage: Usage,
+    /// LLM turns taken in this run
+    pub iterations: usize,
+    pub duration: Duration,
+    /// The final plan and step outcomes, for agents in plan-and-execute mode
+    pub plan: Option<Plan>,
+}
+
+impl AgentRunResult {
+    pub fn is_completed(&self) -> bool {
+        self.outcome == RunOutcome::Completed
+    }
+
+    /// Tool calls made during the run, with their results
+    pub fn tool_calls(&self) -> impl Iterator<Item = (&ToolCall, &str)> {
+        self.steps.iter().filter_map(|step| match step {
+            AgentStep::ToolCall { call, result, .. } => Some((call, result.as_str())),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for AgentRunResult {
+    /// The final answer, or a description of why there is none
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(text) = &self.text {
+            return write!(f, "{}", text);
+        }
+        match self.outcome {
+            RunOutcome::Completed => write!(f, "(No response from agent)"),
+            RunOutcome::MaxIterations => write!(f, "Agent reached max iterations ({})", self.iterations),
+            RunOutcome::MaxTokens => write!(f, "Agent hit max tokens limit"),
+            RunOutcome::ContentFiltered => write!(f, "Agent response was filtered by the provider"),
+            RunOutcome::BudgetExceeded => write!(f, "Agent exceeded its token budget ({} tokens used)", self.usage.total()),
+            RunOutcome::Cancelled => write!(f, "Agent run was cancelled"),
+        }
+    }
+}
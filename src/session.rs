@@ -1,11 +1,59 @@
+use crate::error::{AxonerError, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::provider::Message;
+use crate::provider::{Message, ToolCall};
 
 #[derive(Serialize,Deserialize, Debug, Clone)]
 pub struct Session{
     session_id: String,
     messages: Vec<Message>,
-    time_stamp: String
+    time_stamp: DateTime<Utc>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<ParentLink>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    branches: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<ConversationSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    run_state: Option<RunState>
+}
+
+/// Checkpoint of an agent run that has not finished yet
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RunState {
+    /// Iterations completed so far
+    pub iteration: usize,
+    /// Tool calls requested by the model whose results are not recorded yet
+    pub pending_tool_calls: Vec<ToolCall>,
+    /// Answer text collected so far from responses cut off at the token limit
+    #[serde(default)]
+    pub partial_text: String,
+    /// Continuations requested after hitting the token limit
+    #[serde(default)]
+    pub continuations: usize,
+    /// Agent in control after a handoff, `None` for the agent that started the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_agent: Option<String>,
+}
+
+/// Rolling summary standing in for the oldest messages of a session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConversationSummary {
+    pub text: String,
+    /// Number of leading messages the summary covers
+    pub message_count: usize,
+}
+
+/// Where a forked session branched off its parent
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ParentLink {
+    pub session_id: String,
+    /// Number of parent messages copied into the fork
+    pub message_index: usize,
 }
 
 impl Session {
@@ -14,16 +62,158 @@ impl Session {
         Self{
             session_id,
             messages: Vec::new(),
-            time_stamp:  "2025-11-19T00:00:00Z".to_string(),
+            time_stamp: Utc::now(),
+            updated_at: None,
+            parent: None,
+            branches: Vec::new(),
+            active_branch: None,
+            summary: None,
+            run_state: None,
         }
     }
     pub fn add_message(&mut self, message: Message) {
         self.messages.push(message);
+        self.updated_at = Some(Utc::now());
     }
 
     pub fn get_messages(&self) -> &Vec<Message> {
         &self.messages
     }
 
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// When the session was created
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.time_stamp
+    }
+
+    /// When a message was last added, falls back to the creation time
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at.unwrap_or(self.time_stamp)
+    }
+
+    /// Fork this session into a new session holding the first `at` messages,
+    /// so the conversation can continue differently from message `at` onwards.
+    /// The fork becomes the active branch of this session.
+    pub fn fork(&mut self, at: usize, new_session_id: String) -> Result<Session> {
+        if at > self.messages.len() {
+            return Err(AxonerError::session_store(format!(
+                "Cannot fork session {} at message {}: it only has {} messages",
+                self.session_id, at, self.messages.len()
+            )));
+        }
+        if new_session_id == self.session_id || self.branches.contains(&new_session_id) {
+            return Err(AxonerError::session_store(format!("Session {} already exists", new_session_id)));
+        }
+
+        let mut fork = Session::new(new_session_id.clone());
+        fork.messages = self.messages[..at].to_vec();
+        fork.parent = Some(ParentLink {
+            session_id: self.session_id.clone(),
+            message_index: at,
+        });
+        fork.summary = self.summary.clone().filter(|s| s.message_count <= at);
+
+        self.branches.push(new_session_id.clone());
+        self.active_branch = Some(new_session_id);
+        self.updated_at = Some(Utc::now());
+        Ok(fork)
+    }
+
+    pub fn parent(&self) -> Option<&ParentLink> {
+        self.parent.as_ref()
+    }
+
+    /// Ids of the sessions forked from this one, oldest first
+    pub fn branches(&self) -> &[String] {
+        &self.branches
+    }
+
+    pub fn active_branch(&self) -> Option<&str> {
+        self.active_branch.as_deref()
+    }
+
+    /// Select which fork the active path continues into, `None` to stay on this session
+    pub fn set_active_branch(&mut self, branch: Option<String>) -> Result<()> {
+        if let Some(ref id) = branch
+            && !self.branches.contains(id) {
+            return Err(AxonerError::session_store(format!(
+                "{} is not a branch of session {}",
+                id, self.session_id
+            )));
+        }
+        self.active_branch = branch;
+        Ok(())
+    }
+
+    pub fn summary(&self) -> Option<&ConversationSummary> {
+        self.summary.as_ref()
+    }
+
+    /// Checkpoint of the interrupted run, if the last run did not finish
+    pub fn run_state(&self) -> Option<&RunState> {
+        self.run_state.as_ref()
+    }
+
+    pub fn set_run_state(&mut self, run_state: Option<RunState>) {
+        self.run_state = run_state;
+        self.updated_at = Some(Utc::now());
+    }
 
+    /// Record a summary of the first `message_count` messages
+    pub fn set_summary(&mut self, text: String, message_count: usize) {
+        self.summary = Some(ConversationSummary {
+            text,
+            message_count: message_count.min(self.messages.len()),
+        });
+        self.updated_at = Some(Utc::now());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_with(count: usize) -> Session {
+        let mut session = Session::new("root".to_string());
+        for i in 0..count {
+            session.add_message(Message::user(format!("message {}", i)));
+        }
+        session
+    }
+
+    #[test]
+    fn fork_copies_leading_messages_and_links_parent() {
+        let mut root = session_with(4);
+        let fork = root.fork(2, "branch".to_string()).unwrap();
+
+        assert_eq!(fork.get_messages().len(), 2);
+        assert_eq!(fork.get_messages()[1].content, "message 1");
+        let parent = fork.parent().unwrap();
+        assert_eq!(parent.session_id, "root");
+        assert_eq!(parent.message_index, 2);
+        assert_eq!(root.branches(), ["branch".to_string()]);
+        assert_eq!(root.active_branch(), Some("branch"));
+        assert_eq!(root.get_messages().len(), 4);
+    }
+
+    #[test]
+    fn fork_keeps_only_summaries_within_the_fork() {
+        let mut root = session_with(4);
+        root.set_summary("first three".to_string(), 3);
+        assert!(root.fork(2, "short".to_string()).unwrap().summary().is_none());
+        assert_eq!(root.fork(3, "long".to_string()).unwrap().summary().unwrap().message_count, 3);
+    }
+
+    #[test]
+    fn fork_rejects_out_of_range_and_duplicate_ids() {
+        let mut root = session_with(2);
+        assert!(root.fork(3, "branch".to_string()).is_err());
+        assert!(root.fork(1, "root".to_string()).is_err());
+        root.fork(1, "branch".to_string()).unwrap();
+        assert!(root.fork(1, "branch".to_string()).is_err());
+    }
 }
@@ -0,0 +1,196 @@
+use crate::error::Result;
+use crate::provider::{CompletionResponse, Message, Provider, StopReason, Tool, ToolCall, ToolChoice};
+use crate::tokens::TokenCounter;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// Prefix of a tool call in text, matching how the agent records tool use in
+/// the session so the model sees one consistent format
+const CALL_PREFIX: &str = "Using tool '";
+const INPUT_PREFIX: &str = "' with input:";
+
+/// Drives tools through plain text for models without native function calling.
+/// Tool descriptions are rendered into the system prompt and tool calls are
+/// parsed out of the completion, so the wrapped provider never sees `tools`.
+/// A forced tool choice becomes an instruction, which the model may not follow.
+pub struct TextToolProvider {
+    inner: Box<dyn Provider>,
+}
+
+impl TextToolProvider {
+    pub fn new(inner: Box<dyn Provider>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl Provider for TextToolProvider {
+    async fn complete(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<Tool>>,
+        max_tokens: Option<u32>,
+        system_prompt: Option<String>,
+        tool_choice: Option<ToolChoice>,
+    ) -> Result<CompletionResponse> {
+        let tools = tools.unwrap_or_default();
+        if tools.is_empty() || tool_choice == Some(ToolChoice::None) {
+            return self.inner.complete(messages, None, max_tokens, system_prompt, None).await;
+        }
+
+        let mut instructions = render_tools(&tools);
+        match tool_choice {
+            Some(ToolChoice::Required) => instructions.push_str("\nYou must use at least one tool in this reply."),
+            Some(ToolChoice::Tool(name)) => {
+                instructions.push_str(&format!("\nYou must use the tool '{}' in this reply.", name))
+            }
+            _ => {}
+        }
+        let system_prompt = match system_prompt {
+            Some(prompt) => format!("{}\n\n{}", prompt, instructions),
+            None => instructions,
+        };
+        let mut response = self
+            .inner
+            .complete(messages, None, max_tokens, Some(system_prompt), None)
+            .await?;
+
+        if let Some(ref text) = response.text {
+            let (text, calls) = parse_tool_calls(text, &tools);
+            if !calls.is_empty() {
+                response.text = text;
+                response.tool_calls = calls;
+                response.stop_reason = StopReason::ToolUse;
+            }
+        }
+        Ok(response)
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.inner.model()
+    }
+
+    /// Marked apart from the inner provider, whose requests carry no tools
+    fn fingerprint(&self) -> Value {
+        json!({ "text_tools": self.inner.fingerprint() })
+    }
+
+    fn context_limit(&self) -> Option<u32> {
+        self.inner.context_limit()
+    }
+
+    fn token_counter(&self) -> Box<dyn TokenCounter> {
+        self.inner.token_counter()
+    }
+}
+
+/// Describe the tools and the calling protocol for the system prompt
+pub fn render_tools(tools: &[Tool]) -> String {
+    let descriptions = tools
+        .iter()
+        .map(|t| format!("- {}: {}\n  Input schema: {}", t.name, t.description, t.input_schema))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "You can use these tools:\n{}\n\n\
+To use a tool, write one line per call in exactly this form and then stop:\n\
+{}<tool name>{} <JSON object matching the input schema>\n\
+Each result comes back in a message starting with \"Tool '<tool name>' returned:\". \
+Never write tool results yourself. When you can answer, reply without using a tool.",
+        descriptions, CALL_PREFIX, INPUT_PREFIX
+    )
+}
+
+/// Split a completion into the text before the first tool call and the calls
+/// to known tools. Anything after the last call, such as made-up results, is
+/// dropped.
+pub fn parse_tool_calls(text: &str, tools: &[Tool]) -> (Option<String>, Vec<ToolCall>) {
+    let mut calls = Vec::new();
+    let mut first_call = None;
+    let mut rest = text;
+    let mut offset = 0;
+
+    while let Some(start) = rest.find(CALL_PREFIX) {
+        let after_prefix = &rest[start + CALL_PREFIX.len()..];
+        let Some(name_end) = after_prefix.find(INPUT_PREFIX) else {
+            break;
+        };
+        let name = &after_prefix[..name_end];
+        let input_text = &after_prefix[name_end + INPUT_PREFIX.len()..];
+        let mut values = serde_json::Deserializer::from_str(input_text).into_iter::<Value>();
+
+        let consumed = match values.next() {
+            Some(Ok(input)) if tools.iter().any(|t| t.name == name) => {
+                first_call.get_or_insert(offset + start);
+                calls.push(ToolCall {
+                    id: format!("text_call_{}", calls.len()),
+                    name: name.to_string(),
+                    input,
+                });
+                start + CALL_PREFIX.len() + name_end + INPUT_PREFIX.len() + values.byte_offset()
+            }
+            _ => start + CALL_PREFIX.len(),
+        };
+        offset += consumed;
+        rest = &rest[consumed..];
+    }
+
+    let text = match first_call {
+        Some(end) => Some(text[..end].trim().to_string()).filter(|t| !t.is_empty()),
+        None => Some(text.to_string()),
+    };
+    (text, calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tools() -> Vec<Tool> {
+        ["calculator", "search"]
+            .iter()
+            .map(|name| Tool {
+                name: name.to_string(),
+                description: String::new(),
+                input_schema: json!({ "type": "object" }),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_calls_and_keeps_leading_text() {
+        let text = "Let me work it out.\nUsing tool 'calculator' with input: {\"a\": 1, \"b\": 2}\n\
+Using tool 'search' with input: {\"query\": \"rust\"}";
+        let (text, calls) = parse_tool_calls(text, &tools());
+        assert_eq!(text.as_deref(), Some("Let me work it out."));
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].name, "calculator");
+        assert_eq!(calls[0].input, json!({ "a": 1, "b": 2 }));
+        assert_eq!(calls[1].input, json!({ "query": "rust" }));
+        assert_ne!(calls[0].id, calls[1].id);
+    }
+
+    #[test]
+    fn drops_made_up_results_after_the_calls() {
+        let text = "Using tool 'search' with input: {\"query\": \"x\"}\nTool 'search' returned: fake";
+        let (text, calls) = parse_tool_calls(text, &tools());
+        assert_eq!(text, None);
+        assert_eq!(calls.len(), 1);
+    }
+
+    #[test]
+    fn ignores_unknown_tools_and_invalid_input() {
+        let text = "Using tool 'weather' with input: {}\nUsing tool 'search' with input: not json";
+        let (rest, calls) = parse_tool_calls(text, &tools());
+        assert!(calls.is_empty());
+        assert_eq!(rest.as_deref(), Some(text));
+    }
+
+    #[test]
+    fn plain_answers_have_no_calls() {
+        let (text, calls) = parse_tool_calls("The answer is 3.", &tools());
+        assert_eq!(text.as_deref(), Some("The answer is 3."));
+        assert!(calls.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::provider::{Message, Tool};
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+/// Tokens added around every message by chat formats
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens priming the assistant's reply
+const REPLY_PRIMING_TOKENS: usize = 3;
+/// Tokens of framing added per tool definition
+const TOKENS_PER_TOOL: usize = 8;
+/// Rough cost of an image or document, which can't be counted locally; about
+/// what a full-size image costs on Anthropic and OpenAI
+const TOKENS_PER_PART: usize = 1600;
+
+/// Counts tokens locally, so budgets can be checked before calling a provider
+pub trait TokenCounter: Send + Sync {
+    /// Count the tokens of a piece of text
+    fn count_text(&self, text: &str) -> usize;
+
+    /// Count the tokens of a message list, including per-message framing
+    fn count_messages(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|m| {
+                self.count_text(&m.role)
+                    + self.count_text(&m.content)
+                    + m.parts.len() * TOKENS_PER_PART
+                    + TOKENS_PER_MESSAGE
+            })
+            .sum::<usize>()
+            + REPLY_PRIMING_TOKENS
+    }
+
+    /// Count the tokens of the tool definitions sent with a request
+    fn count_tools(&self, tools: &[Tool]) -> usize {
+        tools
+            .iter()
+            .map(|t| {
+                self.count_text(&t.name)
+                    + self.count_text(&t.description)
+                    + self.count_text(&t.input_schema.to_string())
+                    + TOKENS_PER_TOOL
+            })
+            .sum()
+    }
+}
+
+/// Exact counts with the tiktoken BPE used by OpenAI models. Also used for
+/// OpenAI-compatible models served by Groq, where it is a close estimate.
+pub struct TiktokenCounter {
+    bpe: &'static CoreBPE,
+}
+
+impl TiktokenCounter {
+    /// Pick the encoding for a model name, defaulting to `o200k_base` for unknown models
+    pub fn for_model(model: &str) -> Self {
+        let bpe = match get_tokenizer(model) {
+            Some(Tokenizer::Cl100kBase) => tiktoken_rs::cl100k_base_singleton(),
+            Some(Tokenizer::P50kBase) => tiktoken_rs::p50k_base_singleton(),
+            Some(Tokenizer::P50kEdit) => tiktoken_rs::p50k_edit_singleton(),
+            Some(Tokenizer::R50kBase) | Some(Tokenizer::Gpt2) => tiktoken_rs::r50k_base_singleton(),
+            Some(Tokenizer::O200kBase) | None => tiktoken_rs::o200k_base_singleton(),
+        };
+        Self { bpe }
+    }
+}
+
+impl TokenCounter for TiktokenCounter {
+    fn count_text(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Character-based estimate for models without a public tokenizer
+pub struct ApproximateCounter {
+    chars_per_token: f32,
+}
+
+impl ApproximateCounter {
+    pub fn new(chars_per_token: f32) -> Self {
+        Self { chars_per_token }
+    }
+
+    /// Tuned for Claude models, which average about 3.5 characters per token
+    pub fn anthropic() -> Self {
+        Self::new(3.5)
+    }
+}
+
+impl Default for ApproximateCounter {
+    fn default() -> Self {
+        Self::new(4.0)
+    }
+}
+
+impl TokenCounter for ApproximateCounter {
+    fn count_text(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.chars_per_token).ceil() as usize
+    }
+}
@@ -1,3 +1,4 @@
+use crate::provider::ContentPart;
 use anyhow::Result;
 use serde_json::Value;
 use std::collections::HashMap;
@@ -13,6 +14,34 @@ pub trait Tool: Send + Sync {
     fn input_schema(&self) -> Value;
 
     async fn execute(&self, input: Value) -> Result<String>;
+
+    /// Run the tool and return images or documents along with the text. The
+    /// default returns the text from `execute` without parts.
+    async fn execute_with_parts(&self, input: Value) -> Result<ToolOutput> {
+        Ok(ToolOutput::text(self.execute(input).await?))
+    }
+}
+
+/// What a tool returns to the model
+#[derive(Debug, Clone, Default)]
+pub struct ToolOutput {
+    pub text: String,
+    /// Images or documents, sent with the tool results message
+    pub parts: Vec<ContentPart>,
+}
+
+impl ToolOutput {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            parts: Vec::new(),
+        }
+    }
+
+    pub fn with_part(mut self, part: ContentPart) -> Self {
+        self.parts.push(part);
+        self
+    }
 }
 
 pub struct ToolRegistry {
@@ -31,6 +60,7 @@ impl ToolRegistry {
         self.tools.insert(name, tool);
     }
 
+    #[allow(clippy::borrowed_box)]
     pub fn get(&self, name: &str) -> Option<&Box<dyn Tool>> {
         self.tools.get(name)
     }
@@ -55,4 +85,11 @@ impl Default for ToolRegistry {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Tool names only allow letters, digits, `_` and `-`
+pub(crate) fn sanitize_tool_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
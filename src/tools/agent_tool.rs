@@ -0,0 +1,107 @@
+use crate::agent::Agent;
+use crate::run_result::RunOutcome;
+use crate::session::Session;
+use crate::tool::{sanitize_tool_name, Tool};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+/// Exposes an `Agent` as a tool, so an orchestrator agent can delegate tasks to it
+pub struct AgentTool {
+    agent: Agent,
+    description: String,
+    shared_session: Option<Mutex<Session>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AgentToolInput {
+    task: String,
+    context: Option<String>,
+}
+
+impl AgentTool {
+    /// Each call runs in a fresh session, isolated from earlier calls. The tool
+    /// is named after the agent, so give each delegated agent a name with
+    /// `Agent::with_name`.
+    pub fn new(agent: Agent, description: impl Into<String>) -> Self {
+        Self {
+            agent,
+            description: description.into(),
+            shared_session: None,
+        }
+    }
+
+    /// Keep one session across calls, so the sub-agent remembers earlier tasks
+    pub fn with_shared_session(mut self) -> Self {
+        let session_id = format!("{}-delegated", self.agent.name());
+        self.shared_session = Some(Mutex::new(Session::new(session_id)));
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for AgentTool {
+    /// The agent's name, made safe for providers' tool name rules
+    fn name(&self) -> String {
+        sanitize_tool_name(self.agent.name())
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "task": {
+                    "type": "string",
+                    "description": "The task for the agent, stated completely"
+                },
+                "context": {
+                    "type": "string",
+                    "description": "Background the agent needs, such as earlier findings"
+                }
+            },
+            "required": ["task"]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: AgentToolInput = serde_json::from_value(input)
+            .map_err(|e| anyhow!("Invalid {} input: {}", self.agent.name(), e))?;
+
+        let prompt = match input.context {
+            Some(context) => format!("{}\n\nContext:\n{}", input.task, context),
+            None => input.task,
+        };
+
+        let result = match &self.shared_session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.agent.run_with_session(&mut session, &prompt).await?
+            }
+            None => {
+                let mut session = Session::new(format!("{}-delegated", self.agent.name()));
+                self.agent.run_with_session(&mut session, &prompt).await?
+            }
+        };
+
+        let tools_used: Vec<&str> = result.tool_calls().map(|(call, _)| call.name.as_str()).collect();
+        let mut summary = match result.outcome {
+            RunOutcome::Completed => result.to_string(),
+            ref outcome => format!(
+                "The {} agent did not finish ({:?}). Partial answer: {}",
+                result.answered_by,
+                outcome,
+                result.text.as_deref().unwrap_or("(none)")
+            ),
+        };
+        if !tools_used.is_empty() {
+            summary.push_str(&format!("\n(tools used: {})", tools_used.join(", ")));
+        }
+        Ok(summary)
+    }
+}
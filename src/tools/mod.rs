@@ -1,7 +1,9 @@
 pub mod calculator;
 pub mod websearch;
 pub mod web_scraper;
+pub mod agent_tool;
 
 pub use calculator::Calculator;
 pub use websearch::WebSearch;
-pub use web_scraper::WebScrape;
\ No newline at end of file
+pub use web_scraper::WebScrape;
+pub use agent_tool::AgentTool;
\ No newline at end of file
@@ -76,5 +76,5 @@ async fn parse_html_content(html_body: &str) -> Result<String> {
 }
 async fn fetch_content(link: String) -> Result<String> {
     let html_body = fetch_html(&link).await?;
-    Ok(parse_html_content(&html_body).await?)
+    parse_html_content(&html_body).await
 }
\ No newline at end of file
@@ -63,7 +63,7 @@ fn process_json_data(data: &Value) -> Result<String> {
     //filter(|item| item.key.contains("title") || item.key.contains("link") || item.key.contains("snippet") )
     for item in items {
 
-        search_results.push_str("\n");
+        search_results.push('\n');
         search_results.push_str(&format!("{},{},{}", &item["title"], &item["link"], &item["snippet"]));
 
         println!("Matched item: Title: {}", &item["title"]);
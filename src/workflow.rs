@@ -0,0 +1,524 @@
+use crate::agent::Agent;
+use crate::error::{AxonerError, Result};
+use crate::executor::ToolExecutor;
+use crate::provider::{Message, Provider, ToolCall};
+use crate::run_result::AgentRunResult;
+use crate::tool::ToolRegistry;
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Target name that ends a workflow run
+pub const END: &str = "__end__";
+
+/// A step of a workflow, transforming the shared state
+#[async_trait]
+pub trait Node<S: Send + 'static>: Send + Sync {
+    async fn run(&self, state: S) -> Result<S>;
+}
+
+/// Where a workflow goes after a node
+enum Edge<S> {
+    Direct(String),
+    Conditional(Box<dyn Fn(&S) -> String + Send + Sync>),
+    /// Run the branch nodes concurrently on copies of the state, merge their
+    /// states and continue at `join`
+    FanOut {
+        branches: Vec<String>,
+        join: String,
+        merge: Box<dyn Fn(S, Vec<S>) -> S + Send + Sync>,
+    },
+}
+
+/// A deterministic pipeline of nodes connected by (conditional) edges over a
+/// typed shared state
+pub struct Workflow<S> {
+    nodes: HashMap<String, Box<dyn Node<S>>>,
+    edges: HashMap<String, Edge<S>>,
+    entry: Option<String>,
+    max_steps: usize,
+    max_visits: HashMap<String, usize>,
+    checkpoint_path: Option<PathBuf>,
+}
+
+/// Progress of a workflow run, saved after every node when checkpointing is on
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkflowCheckpoint<S> {
+    /// Node to run next, `None` once the run has finished
+    pub next: Option<String>,
+    pub state: S,
+    /// Nodes run so far, in order
+    pub path: Vec<String>,
+}
+
+/// Final state of a workflow run
+#[derive(Debug, Clone)]
+pub struct WorkflowRun<S> {
+    pub state: S,
+    /// Nodes run, in order; fan-out branches appear in declaration order
+    pub path: Vec<String>,
+}
+
+impl<S> Workflow<S>
+where
+    S: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+            entry: None,
+            max_steps: 100, // Prevent runaway loops
+            max_visits: HashMap::new(),
+            checkpoint_path: None,
+        }
+    }
+
+    pub fn add_node(&mut self, name: &str, node: impl Node<S> + 'static) {
+        self.nodes.insert(name.to_string(), Box::new(node));
+    }
+
+    /// Node the workflow starts at
+    pub fn set_entry(&mut self, name: &str) {
+        self.entry = Some(name.to_string());
+    }
+
+    /// Always continue from `from` to `to` (or `END`)
+    pub fn add_edge(&mut self, from: &str, to: &str) {
+        self.edges.insert(from.to_string(), Edge::Direct(to.to_string()));
+    }
+
+    /// Continue from `from` to the node named by `route` (or `END`).
+    /// Routing back to an earlier node creates a loop.
+    pub fn add_conditional_edge(&mut self, from: &str, route: impl Fn(&S) -> String + Send + Sync + 'static) {
+        self.edges.insert(from.to_string(), Edge::Conditional(Box::new(route)));
+    }
+
+    /// After `from`, run `branches` concurrently, each on a copy of the state, then
+    /// combine the results with `merge` and continue at `join`. Edges leaving the
+    /// branch nodes are not followed.
+    pub fn add_fan_out(
+        &mut self,
+        from: &str,
+        branches: &[&str],
+        join: &str,
+        merge: impl Fn(S, Vec<S>) -> S + Send + Sync + 'static,
+    ) {
+        self.edges.insert(
+            from.to_string(),
+            Edge::FanOut {
+                branches: branches.iter().map(|b| b.to_string()).collect(),
+                join: join.to_string(),
+                merge: Box::new(merge),
+            },
+        );
+    }
+
+    /// Maximum number of nodes a run may execute (default 100)
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    /// Maximum number of times a node may run, to bound loops through it
+    pub fn set_max_visits(&mut self, node: &str, max_visits: usize) {
+        self.max_visits.insert(node.to_string(), max_visits);
+    }
+
+    /// Save a checkpoint to `path` after every node, so `resume` can continue
+    /// an interrupted run
+    pub fn set_checkpoint_path(&mut self, path: PathBuf) {
+        self.checkpoint_path = Some(path);
+    }
+
+    /// Run the workflow from its entry node
+    pub async fn run(&self, state: S) -> Result<WorkflowRun<S>> {
+        let entry = self
+            .entry
+            .clone()
+            .ok_or_else(|| AxonerError::Workflow("No entry node set".to_string()))?;
+        self.execute(WorkflowCheckpoint {
+            next: Some(entry),
+            state,
+            path: Vec::new(),
+        })
+        .await
+    }
+
+    /// Continue the run saved at the checkpoint path
+    pub async fn resume(&self) -> Result<WorkflowRun<S>> {
+        let path = self
+            .checkpoint_path
+            .as_ref()
+            .ok_or_else(|| AxonerError::Workflow("No checkpoint path set".to_string()))?;
+        let checkpoint: WorkflowCheckpoint<S> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        self.execute(checkpoint).await
+    }
+
+    async fn execute(&self, mut checkpoint: WorkflowCheckpoint<S>) -> Result<WorkflowRun<S>> {
+        while let Some(current) = checkpoint.next.take() {
+            if current == END {
+                break;
+            }
+            if checkpoint.path.len() >= self.max_steps {
+                return Err(AxonerError::Workflow(format!(
+                    "Exceeded max steps ({}) before running '{}'",
+                    self.max_steps, current
+                )));
+            }
+            self.check_visits(&checkpoint.path, &current)?;
+
+            let state = self.node(&current)?.run(checkpoint.state.clone()).await?;
+            checkpoint.path.push(current.clone());
+
+            let (state, next) = match self.edges.get(&current) {
+                None => (state, None),
+                Some(Edge::Direct(to)) => (state, Some(to.clone())),
+                Some(Edge::Conditional(route)) => {
+                    let next = route(&state);
+                    (state, Some(next))
+                }
+                Some(Edge::FanOut { branches, join, merge }) => {
+                    if checkpoint.path.len() + branches.len() > self.max_steps {
+                        return Err(AxonerError::Workflow(format!(
+                            "Exceeded max steps ({}) before fanning out from '{}'",
+                            self.max_steps, current
+                        )));
+                    }
+                    for branch in branches {
+                        self.check_visits(&checkpoint.path, branch)?;
+                    }
+                    let runs = branches
+                        .iter()
+                        .map(|b| Ok(self.node(b)?.run(state.clone())))
+                        .collect::<Result<Vec<_>>>()?;
+                    let branch_states = try_join_all(runs).await?;
+                    checkpoint.path.extend(branches.iter().cloned());
+                    (merge(state, branch_states), Some(join.clone()))
+                }
+            };
+            checkpoint.state = state;
+            checkpoint.next = next;
+            self.save_checkpoint(&checkpoint)?;
+        }
+
+        checkpoint.next = None;
+        self.save_checkpoint(&checkpoint)?;
+        Ok(WorkflowRun {
+            state: checkpoint.state,
+            path: checkpoint.path,
+        })
+    }
+
+    fn node(&self, name: &str) -> Result<&dyn Node<S>> {
+        self.nodes
+            .get(name)
+            .map(|node| node.as_ref())
+            .ok_or_else(|| AxonerError::Workflow(format!("Unknown node '{}'", name)))
+    }
+
+    fn check_visits(&self, path: &[String], node: &str) -> Result<()> {
+        if let Some(&max) = self.max_visits.get(node)
+            && path.iter().filter(|n| *n == node).count() >= max
+        {
+            return Err(AxonerError::Workflow(format!(
+                "Node '{}' exceeded its max visits ({})",
+                node, max
+            )));
+        }
+        Ok(())
+    }
+
+    fn save_checkpoint(&self, checkpoint: &WorkflowCheckpoint<S>) -> Result<()> {
+        if let Some(ref path) = self.checkpoint_path {
+            fs::write(path, serde_json::to_string(checkpoint)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> Default for Workflow<S>
+where
+    S: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a prompt from the workflow state
+type PromptFn<S> = Box<dyn Fn(&S) -> String + Send + Sync>;
+/// Writes a node's output back into the workflow state
+type ApplyFn<S, T> = Box<dyn Fn(&mut S, T) + Send + Sync>;
+
+/// Calls the LLM with a prompt built from the state and stores the reply
+pub struct LlmNode<S> {
+    provider: Box<dyn Provider>,
+    system_prompt: Option<String>,
+    prompt: PromptFn<S>,
+    apply: ApplyFn<S, String>,
+}
+
+impl<S> LlmNode<S> {
+    pub fn new(
+        provider: Box<dyn Provider>,
+        prompt: impl Fn(&S) -> String + Send + Sync + 'static,
+        apply: impl Fn(&mut S, String) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            provider,
+            system_prompt: None,
+            prompt: Box::new(prompt),
+            apply: Box::new(apply),
+        }
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: String) -> Self {
+        self.system_prompt = Some(system_prompt);
+        self
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync + 'static> Node<S> for LlmNode<S> {
+    async fn run(&self, mut state: S) -> Result<S> {
+        let message = Message::user((self.prompt)(&state));
+        let response = self
+            .provider
+            .complete(vec![message], None, None, self.system_prompt.clone(), None)
+            .await?;
+        (self.apply)(&mut state, response.text.unwrap_or_default());
+        Ok(state)
+    }
+}
+
+/// Runs one tool from a registry with input built from the state
+pub struct ToolNode<S> {
+    registry: Arc<ToolRegistry>,
+    tool_name: String,
+    input: Box<dyn Fn(&S) -> Value + Send + Sync>,
+    apply: ApplyFn<S, String>,
+}
+
+impl<S> ToolNode<S> {
+    pub fn new(
+        registry: Arc<ToolRegistry>,
+        tool_name: &str,
+        input: impl Fn(&S) -> Value + Send + Sync + 'static,
+        apply: impl Fn(&mut S, String) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            registry,
+            tool_name: tool_name.to_string(),
+            input: Box::new(input),
+            apply: Box::new(apply),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync + 'static> Node<S> for ToolNode<S> {
+    async fn run(&self, mut state: S) -> Result<S> {
+        let call = ToolCall {
+            id: format!("workflow-{}", self.tool_name),
+            name: self.tool_name.clone(),
+            input: (self.input)(&state),
+        };
+        let result = ToolExecutor::new(&self.registry).execute(&call).await?;
+        (self.apply)(&mut state, result.result);
+        Ok(state)
+    }
+}
+
+/// Runs an agent on a prompt built from the state
+pub struct AgentNode<S> {
+    agent: Agent,
+    prompt: PromptFn<S>,
+    apply: ApplyFn<S, AgentRunResult>,
+}
+
+impl<S> AgentNode<S> {
+    pub fn new(
+        agent: Agent,
+        prompt: impl Fn(&S) -> String + Send + Sync + 'static,
+        apply: impl Fn(&mut S, AgentRunResult) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            agent,
+            prompt: Box::new(prompt),
+            apply: Box::new(apply),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync + 'static> Node<S> for AgentNode<S> {
+    async fn run(&self, mut state: S) -> Result<S> {
+        let result = self.agent.run(&(self.prompt)(&state)).await?;
+        (self.apply)(&mut state, result);
+        Ok(state)
+    }
+}
+
+/// Runs an async Rust function over the state
+pub struct FnNode<F> {
+    f: F,
+}
+
+impl<F> FnNode<F> {
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+#[async_trait]
+impl<S, F, Fut> Node<S> for FnNode<F>
+where
+    S: Send + 'static,
+    F: Fn(S) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<S>> + Send,
+{
+    async fn run(&self, state: S) -> Result<S> {
+        (self.f)(state).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProvider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    type Log = Vec<String>;
+
+    /// A node that appends its name to the state
+    fn push(name: &'static str) -> impl Node<Log> {
+        FnNode::new(move |mut state: Log| async move {
+            state.push(name.to_string());
+            Ok(state)
+        })
+    }
+
+    #[tokio::test]
+    async fn conditional_loops_run_until_routed_to_end() {
+        let mut workflow = Workflow::new();
+        workflow.add_node("step", push("step"));
+        workflow.set_entry("step");
+        workflow.add_conditional_edge("step", |state: &Log| {
+            if state.len() < 3 { "step".to_string() } else { END.to_string() }
+        });
+
+        let run = workflow.run(Vec::new()).await.unwrap();
+        assert_eq!(run.path, vec!["step", "step", "step"]);
+    }
+
+    #[tokio::test]
+    async fn loops_stop_at_max_visits_and_max_steps() {
+        let mut workflow = Workflow::new();
+        workflow.add_node("step", push("step"));
+        workflow.set_entry("step");
+        workflow.add_edge("step", "step");
+
+        workflow.set_max_steps(5);
+        let err = workflow.run(Vec::new()).await.unwrap_err();
+        assert!(matches!(err, AxonerError::Workflow(ref m) if m.contains("max steps (5)")));
+
+        workflow.set_max_visits("step", 2);
+        let err = workflow.run(Vec::new()).await.unwrap_err();
+        assert!(matches!(err, AxonerError::Workflow(ref m) if m.contains("max visits (2)")));
+    }
+
+    #[tokio::test]
+    async fn fan_out_merges_branches_in_declaration_order() {
+        let mut workflow = Workflow::new();
+        workflow.add_node("start", push("start"));
+        // The first branch finishes last
+        workflow.add_node(
+            "slow",
+            FnNode::new(|mut state: Log| async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                state.push("slow".to_string());
+                Ok(state)
+            }),
+        );
+        workflow.add_node("fast", push("fast"));
+        workflow.add_node("join", push("join"));
+        workflow.set_entry("start");
+        workflow.add_fan_out("start", &["slow", "fast"], "join", |mut state, branches| {
+            for branch in branches {
+                state.push(branch.last().unwrap().clone());
+            }
+            state
+        });
+        workflow.add_edge("join", END);
+
+        let run = workflow.run(Vec::new()).await.unwrap();
+        assert_eq!(run.state, vec!["start", "slow", "fast", "join"]);
+        assert_eq!(run.path, vec!["start", "slow", "fast", "join"]);
+    }
+
+    #[tokio::test]
+    async fn fan_out_counts_branches_against_max_steps() {
+        let mut workflow = Workflow::new();
+        workflow.add_node("start", push("start"));
+        workflow.add_node("a", push("a"));
+        workflow.add_node("b", push("b"));
+        workflow.set_entry("start");
+        workflow.add_fan_out("start", &["a", "b"], "start", |state, _| state);
+        workflow.set_max_steps(5);
+
+        let err = workflow.run(Vec::new()).await.unwrap_err();
+        assert!(matches!(err, AxonerError::Workflow(ref m) if m.contains("fanning out")));
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_last_checkpoint() {
+        let path = std::env::temp_dir().join(format!("axonerai-test-{}.json", uuid::Uuid::new_v4()));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let flaky_attempts = attempts.clone();
+
+        let mut workflow = Workflow::new();
+        workflow.add_node("first", push("first"));
+        workflow.add_node(
+            "flaky",
+            FnNode::new(move |mut state: Log| {
+                let attempt = flaky_attempts.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt == 0 {
+                        return Err(anyhow::anyhow!("temporary failure").into());
+                    }
+                    state.push("flaky".to_string());
+                    Ok(state)
+                }
+            }),
+        );
+        workflow.add_node(
+            "summary",
+            LlmNode::new(
+                Box::new(MockProvider::new().with_text("done")),
+                |state: &Log| state.join(", "),
+                |state, reply| state.push(reply),
+            ),
+        );
+        workflow.set_entry("first");
+        workflow.add_edge("first", "flaky");
+        workflow.add_edge("flaky", "summary");
+        workflow.add_edge("summary", END);
+        workflow.set_checkpoint_path(path.clone());
+
+        assert!(workflow.run(Vec::new()).await.is_err());
+        let run = workflow.resume().await.unwrap();
+
+        assert_eq!(run.state, vec!["first", "flaky", "done"]);
+        assert_eq!(run.path, vec!["first", "flaky", "summary"]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        fs::remove_file(path).unwrap();
+    }
+}